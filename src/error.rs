@@ -0,0 +1,242 @@
+use std::{fmt::Display, io};
+
+use serde_json::{json, Value};
+use zbus::DBusError;
+
+/// Error of every command, sorted by what a widget can make of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The bus itself can't be reached
+    DBusUnavailable(String),
+    /// The service (NetworkManager, ModemManager, Hyprland...) isn't running
+    ServiceMissing(String),
+    /// An object was removed between its listing and its use
+    ObjectVanished(String),
+    /// A value couldn't be decoded
+    Parse(String),
+    PermissionDenied(String),
+    Other(String),
+}
+
+impl Error {
+    /// Name of the variant, as output in the error json
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::DBusUnavailable(_) => "dbusUnavailable",
+            Error::ServiceMissing(_) => "serviceMissing",
+            Error::ObjectVanished(_) => "objectVanished",
+            Error::Parse(_) => "parse",
+            Error::PermissionDenied(_) => "permissionDenied",
+            Error::Other(_) => "other",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Error::DBusUnavailable(message)
+            | Error::ServiceMissing(message)
+            | Error::ObjectVanished(message)
+            | Error::Parse(message)
+            | Error::PermissionDenied(message)
+            | Error::Other(message) => message,
+        }
+    }
+
+    /// Whether a listener can go on with the next event
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            Error::ObjectVanished(_) | Error::Parse(_) | Error::Other(_)
+        )
+    }
+
+    /// ``` json
+    /// {"error": {"kind": "serviceMissing", "message": "The name org.freedesktop.NetworkManager was not provided by any .service files"}}
+    /// ```
+    pub fn to_json(&self) -> Value {
+        json!({
+            "error": {
+                "kind": self.kind(),
+                "message": self.message(),
+            }
+        })
+    }
+
+    /// Sort a D-Bus error reply by its name
+    fn from_dbus_name(name: &str, description: Option<&str>) -> Error {
+        let message = match description {
+            Some(description) => format!("{}: {}", name, description),
+            None => name.to_string(),
+        };
+        match name.rsplit('.').next().unwrap_or(name) {
+            "ServiceUnknown" | "NameHasNoOwner" => Error::ServiceMissing(message),
+            "UnknownObject" | "UnknownInterface" | "UnknownProperty" | "UnknownMethod" => {
+                Error::ObjectVanished(message)
+            }
+            "AccessDenied"
+            | "AuthFailed"
+            | "InteractiveAuthorizationRequired"
+            | "PermissionDenied" => Error::PermissionDenied(message),
+            "InvalidArgs" | "InvalidSignature" => Error::Parse(message),
+            _ => Error::Other(message),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::DBusUnavailable(message) => write!(f, "D-Bus unavailable: {}", message),
+            Error::ServiceMissing(message) => write!(f, "service missing: {}", message),
+            Error::ObjectVanished(message) => write!(f, "object vanished: {}", message),
+            Error::Parse(message) => write!(f, "parse failure: {}", message),
+            Error::PermissionDenied(message) => write!(f, "permission denied: {}", message),
+            Error::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Print a recoverable error as a json line instead of stopping the listener
+pub fn report(result: Result<(), Error>) -> Result<(), Error> {
+    match result {
+        Err(error) if error.is_recoverable() => {
+            println!("{}", error.to_json());
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+impl From<zbus::Error> for Error {
+    fn from(error: zbus::Error) -> Self {
+        match error {
+            zbus::Error::InputOutput(error) => match error.kind() {
+                io::ErrorKind::PermissionDenied => Error::PermissionDenied(error.to_string()),
+                _ => Error::DBusUnavailable(error.to_string()),
+            },
+            zbus::Error::Address(message) | zbus::Error::Handshake(message) => {
+                Error::DBusUnavailable(message)
+            }
+            zbus::Error::MethodError(name, description, _) => {
+                Error::from_dbus_name(name.as_str(), description.as_deref())
+            }
+            zbus::Error::FDO(error) => Error::from(*error),
+            zbus::Error::InterfaceNotFound => Error::ObjectVanished(error.to_string()),
+            zbus::Error::Variant(error) => Error::from(error),
+            zbus::Error::InvalidReply
+            | zbus::Error::InvalidField
+            | zbus::Error::NoBodySignature
+            | zbus::Error::Names(_) => Error::Parse(error.to_string()),
+            error => Error::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<zbus::fdo::Error> for Error {
+    fn from(error: zbus::fdo::Error) -> Self {
+        match error {
+            zbus::fdo::Error::ZBus(error) => Error::from(error),
+            error => Error::from_dbus_name(error.name().as_str(), error.description()),
+        }
+    }
+}
+
+impl From<zvariant::Error> for Error {
+    fn from(error: zvariant::Error) -> Self {
+        Error::Parse(error.to_string())
+    }
+}
+
+impl From<zbus::names::Error> for Error {
+    fn from(error: zbus::names::Error) -> Self {
+        Error::Parse(error.to_string())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied(error.to_string()),
+            _ => Error::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<hyprland::shared::HyprError> for Error {
+    fn from(error: hyprland::shared::HyprError) -> Self {
+        match error {
+            hyprland::shared::HyprError::IoError(error) => match error.kind() {
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => {
+                    Error::ServiceMissing(format!("Hyprland: {}", error))
+                }
+                _ => Error::from(error),
+            },
+            hyprland::shared::HyprError::SerdeError(error) => Error::from(error),
+            hyprland::shared::HyprError::FromUtf8Error(error) => Error::Parse(error.to_string()),
+            hyprland::shared::HyprError::NotOkDispatch(message) => Error::Other(message),
+        }
+    }
+}
+
+impl From<surge_ping::SurgeError> for Error {
+    fn from(error: surge_ping::SurgeError) -> Self {
+        match error {
+            surge_ping::SurgeError::IOError(error) => Error::from(error),
+            surge_ping::SurgeError::MalformedPacket(_) => Error::Parse(error.to_string()),
+            error => Error::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        match error.is_decode() {
+            true => Error::Parse(error.to_string()),
+            false => Error::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Parse(error.to_string())
+    }
+}
+
+impl From<std::str::Utf8Error> for Error {
+    fn from(error: std::str::Utf8Error) -> Self {
+        Error::Parse(error.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(error: std::num::ParseIntError) -> Self {
+        Error::Parse(error.to_string())
+    }
+}
+
+impl From<std::net::AddrParseError> for Error {
+    fn from(error: std::net::AddrParseError) -> Self {
+        Error::Parse(error.to_string())
+    }
+}
+
+impl From<clap::Error> for Error {
+    fn from(error: clap::Error) -> Self {
+        Error::Parse(error.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Other(message.to_string())
+    }
+}
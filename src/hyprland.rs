@@ -1,10 +1,138 @@
 use hyprland::{
-    data::{Devices, Workspace, Workspaces},
+    data::{Client, Devices, Monitor, Monitors, Workspace, Workspaces},
     event_listener::EventListener,
-    shared::{HyprData, HyprDataActive},
+    shared::{HyprData, HyprDataActive, HyprDataActiveOptional},
 };
 use serde_json::{json, Value};
-use std::error::Error;
+use std::{
+    ffi::{OsStr, OsString},
+    os::unix::net::UnixStream,
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::error::Error;
+
+/// Where the hyprland crate looks for the sockets of the Hyprland instances
+const INSTANCES_DIR: &str = "/tmp/hypr";
+
+/// Whether the command socket of the instance `signature` accepts connections
+fn is_instance_up(signature: &OsStr) -> bool {
+    let socket = Path::new(INSTANCES_DIR).join(signature).join(".socket.sock");
+    UnixStream::connect(socket).is_ok()
+}
+
+/// Point the hyprland crate at a running Hyprland instance: the one of
+/// `HYPRLAND_INSTANCE_SIGNATURE` while it is up, otherwise the newest one, as the signature
+/// changes when Hyprland is restarted after a crash.
+///
+/// The hyprland crate panics instead of failing when there is no instance, or when its command
+/// socket can't be reached, so both are checked first. The environment is only changed from
+/// the Hyprland listeners, which run on the main thread without any tokio runtime.
+fn find_instance() -> Result<(), Error> {
+    if let Some(signature) = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE") {
+        if is_instance_up(&signature) {
+            return Ok(());
+        }
+    }
+    let mut instances: Vec<(SystemTime, OsString)> = std::fs::read_dir(INSTANCES_DIR)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            if !metadata.is_dir() {
+                return None;
+            }
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((modified, entry.file_name()))
+        })
+        .collect();
+    // Newest first
+    instances.sort_by(|(modified1, _), (modified2, _)| modified2.cmp(modified1));
+    match instances
+        .into_iter()
+        .find(|(_, signature)| is_instance_up(signature))
+    {
+        Some((_, signature)) => {
+            std::env::set_var("HYPRLAND_INSTANCE_SIGNATURE", signature);
+            Ok(())
+        }
+        None => Err(Error::ServiceMissing(format!(
+            "Hyprland: no running instance under {}",
+            INSTANCES_DIR
+        ))),
+    }
+}
+
+/// Format of the lines printed by a listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Json,
+    Text,
+}
+
+impl Output {
+    /// Print `error` without breaking the format: as an error json, or as an empty line
+    /// with the message on stderr
+    fn report(&self, error: &Error) {
+        match self {
+            Output::Json => println!("{}", error.to_json()),
+            Output::Text => {
+                println!();
+                eprintln!("{}", error);
+            }
+        }
+    }
+}
+
+/// Delay before the first reconnection to the event socket, doubled after each failure
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Print `snapshot` then run the event listener built by `event_listener` until its socket
+/// closes, e.g. when Hyprland reloads or crashes. The disconnection is reported in the `output`
+/// format, then both are run again after a backoff, against the newest instance.
+fn reconnecting(
+    output: Output,
+    snapshot: impl Fn() -> Result<(), Error>,
+    event_listener: impl Fn() -> EventListener,
+) -> Result<(), Error> {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        let connected = Instant::now();
+        let reason = match find_instance()
+            .and_then(|_| snapshot())
+            .and_then(|_| Ok(event_listener().start_listener()?))
+        {
+            Ok(()) => "Hyprland: event socket closed".to_string(),
+            Err(error) => error.message().to_string(),
+        };
+        // The previous connection held, start the backoff over
+        if connected.elapsed() > MAX_BACKOFF {
+            backoff = MIN_BACKOFF;
+        }
+        let disconnected = Error::ServiceMissing(format!(
+            "{}, reconnecting in {}ms",
+            reason,
+            backoff.as_millis()
+        ));
+        output.report(&disconnected);
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Which workspaces `workspaces_listener` outputs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum WorkspacesScope {
+    /// Every workspace, with the focused one
+    All,
+    /// The workspaces of one monitor, for a bar per monitor
+    Monitor(String),
+    /// The workspaces of every monitor, keyed by monitor name
+    PerMonitor,
+}
 
 /// Output a json whenever a change ocur
 ///
@@ -15,31 +143,67 @@ use std::error::Error;
 /// }
 ///
 /// ```
-pub(crate) fn workspaces_listener() -> Result<(), Box<dyn Error>> {
-    let mut event_listener = EventListener::new();
+///
+/// With [`WorkspacesScope::Monitor`], the workspaces of the monitor sorted by id,
+/// `active_workspace` being the one shown on the monitor and `focused` whether the monitor is
+/// the focused one. A disconnected monitor has no workspace.
+///
+/// ``` json
+/// {
+///  "monitor":"DP-1",
+///  "focused":true,
+///  "active_workspace":2,
+///  "workspaces":[{"id":1,"windows":3},{"id":2,"windows":0}]
+/// }
+/// ```
+///
+/// With [`WorkspacesScope::PerMonitor`], the same for every monitor, keyed by name.
+///
+/// ``` json
+/// {
+///  "focused_monitor":"DP-1",
+///  "monitors":{
+///   "DP-1":{"focused":true,"active_workspace":2,"workspaces":[{"id":1,"windows":3},{"id":2,"windows":0}]},
+///   "HDMI-A-1":{"focused":false,"active_workspace":5,"workspaces":[{"id":5,"windows":1}]}
+///  }
+/// }
+/// ```
+pub(crate) fn workspaces_listener(scope: WorkspacesScope) -> Result<(), Error> {
+    reconnecting(
+        Output::Json,
+        || {
+            println!("{}", serialize_workspaces(&scope)?);
+            Ok(())
+        },
+        || {
+            let mut event_listener = EventListener::new();
 
-    println!("{}", serialize_workspaces()?);
+            event_listener.add_workspace_added_handler(workspaces_printer(&scope));
 
-    event_listener.add_workspace_added_handler(|_| match serialize_workspaces() {
-        Err(_) => {}
-        Ok(out) => println!("{}", out),
-    });
+            event_listener.add_workspace_change_handler(workspaces_printer(&scope));
 
-    event_listener.add_workspace_change_handler(|_| match serialize_workspaces() {
-        Err(_) => {}
-        Ok(out) => println!("{}", out),
-    });
+            event_listener.add_workspace_destroy_handler(workspaces_printer(&scope));
 
-    event_listener.add_workspace_destroy_handler(|_| match serialize_workspaces() {
-        Err(_) => {}
-        Ok(out) => println!("{}", out),
-    });
+            event_listener.add_workspace_moved_handler(workspaces_printer(&scope));
+
+            event_listener.add_active_monitor_change_handler(workspaces_printer(&scope));
+
+            event_listener.add_monitor_added_handler(workspaces_printer(&scope));
 
-    let _ = event_listener.start_listener();
-    Ok(())
+            event_listener.add_monitor_removed_handler(workspaces_printer(&scope));
+
+            event_listener
+        },
+    )
+}
+
+/// Event handler printing the workspaces of `scope`, whatever the event
+fn workspaces_printer<T>(scope: &WorkspacesScope) -> impl Fn(T) + 'static {
+    let scope = scope.clone();
+    move |_| print_workspaces(&scope)
 }
 
-fn get_workspaces_id() -> Result<Vec<Value>, Box<dyn Error>> {
+fn get_workspaces_id() -> Result<Vec<Value>, Error> {
     let workspaces = Workspaces::get()?;
     let workspaces_id = workspaces
         .map(|workspace| {
@@ -52,12 +216,77 @@ fn get_workspaces_id() -> Result<Vec<Value>, Box<dyn Error>> {
     Ok(workspaces_id)
 }
 
-fn get_workspaces_active_id() -> Result<i32, Box<dyn Error>> {
+fn get_workspaces_active_id() -> Result<i32, Error> {
     let workspace = Workspace::get_active()?;
     Ok(workspace.id)
 }
 
-fn serialize_workspaces() -> Result<String, Box<dyn Error>> {
+fn get_monitors_workspaces() -> Result<(Vec<Monitor>, Vec<Workspace>), Error> {
+    let monitors = Monitors::get()?.collect();
+    let workspaces = Workspaces::get()?.collect();
+    Ok((monitors, workspaces))
+}
+
+/// Workspaces of the monitor `name`, `monitor` being `None` when it is disconnected
+fn monitor_workspaces(name: &str, monitor: Option<&Monitor>, workspaces: &[Workspace]) -> Value {
+    let mut workspaces = workspaces
+        .iter()
+        .filter(|workspace| workspace.monitor == name)
+        .collect::<Vec<_>>();
+    workspaces.sort_by_key(|workspace| workspace.id);
+    json!({
+        "focused": monitor.is_some_and(|monitor| monitor.focused),
+        "active_workspace": monitor.map(|monitor| monitor.active_workspace.id),
+        "workspaces": workspaces
+            .iter()
+            .map(|workspace| {
+                json!({
+                    "id": workspace.id,
+                    "windows": workspace.windows,
+                })
+            })
+            .collect::<Vec<Value>>(),
+    })
+}
+
+/// The workspaces, or the error when Hyprland couldn't be queried
+fn print_workspaces(scope: &WorkspacesScope) {
+    match serialize_workspaces(scope) {
+        Ok(out) => println!("{}", out),
+        Err(error) => println!("{}", error.to_json()),
+    }
+}
+
+fn serialize_workspaces(scope: &WorkspacesScope) -> Result<String, Error> {
+    match scope {
+        WorkspacesScope::All => serialize_all_workspaces(),
+        WorkspacesScope::Monitor(name) => {
+            let (monitors, workspaces) = get_monitors_workspaces()?;
+            let monitor = monitors.iter().find(|monitor| monitor.name == *name);
+            let mut data = monitor_workspaces(name, monitor, &workspaces);
+            data["monitor"] = json!(name);
+            Ok(data.to_string())
+        }
+        WorkspacesScope::PerMonitor => {
+            let (monitors, workspaces) = get_monitors_workspaces()?;
+            let focused_monitor = monitors.iter().find(|monitor| monitor.focused);
+            let per_monitor = monitors
+                .iter()
+                .map(|monitor| {
+                    let data = monitor_workspaces(&monitor.name, Some(monitor), &workspaces);
+                    (monitor.name.clone(), data)
+                })
+                .collect::<serde_json::Map<String, Value>>();
+            Ok(json!({
+                "focused_monitor": focused_monitor.map(|monitor| &monitor.name),
+                "monitors": per_monitor,
+            })
+            .to_string())
+        }
+    }
+}
+
+fn serialize_all_workspaces() -> Result<String, Error> {
     let workspaces = get_workspaces_id()?;
     let active_workspace = get_workspaces_active_id()?;
 
@@ -68,32 +297,141 @@ fn serialize_workspaces() -> Result<String, Box<dyn Error>> {
     .to_string())
 }
 
-pub(crate) fn active_window_listener() -> Result<(), Box<dyn Error>> {
-    let mut event_listener = EventListener::new();
+pub(crate) fn active_window_listener() -> Result<(), Error> {
+    reconnecting(
+        Output::Text,
+        || {
+            if let Some(active_window) = Client::get_active()? {
+                println!("{}", active_window.title)
+            }
+            Ok(())
+        },
+        || {
+            let mut event_listener = EventListener::new();
 
-    event_listener.add_active_window_change_handler(|a| {
-        if let Some(active_window) = a {
-            println!("{}", active_window.window_title)
-        }
-    });
+            event_listener.add_active_window_change_handler(|a| {
+                if let Some(active_window) = a {
+                    println!("{}", active_window.window_title)
+                }
+            });
+
+            event_listener
+        },
+    )
+}
 
-    let _ = event_listener.start_listener();
-    Ok(())
+pub(crate) fn keyboard_language_listener() -> Result<(), Error> {
+    reconnecting(
+        Output::Text,
+        || {
+            let keybord_devices = Devices::get()?.keyboards;
+
+            let kb = keybord_devices.iter().filter(|kb| kb.name.contains("(kb)")).min_by(|kb1,kb2| {
+                kb1.name.cmp(&kb2.name)
+            }).ok_or("no keyboard found")?;
+            println!("{}", kb.active_keymap);
+            Ok(())
+        },
+        || {
+            let mut event_listener = EventListener::new();
+
+            // The event data is `keyboard_name,layout_name`
+            event_listener.add_keyboard_layout_change_handler(|layout| {
+                match layout.keyboard_name.split_once(',') {
+                    Some((_, layout)) => println!("{}", layout),
+                    None => {
+                        let error = Error::Parse(format!("unexpected layout event: {}", layout.keyboard_name));
+                        eprintln!("{}", error)
+                    }
+                }
+            });
+
+            event_listener
+        },
+    )
 }
 
-pub(crate) fn keyboard_language_listener() -> Result<(), Box<dyn Error>> {
-    let mut event_listener = EventListener::new();
-    let keybord_devices = Devices::get()?.keyboards;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let kb = keybord_devices.iter().filter(|kb| kb.name.contains("(kb)")).min_by(|kb1,kb2| {
-        kb1.name.cmp(&kb2.name)
-    }).unwrap();
-    println!("{}", kb.active_keymap);
+    /// A workspace as output by `hyprctl workspaces -j`
+    fn workspace(id: i32, monitor: &str, windows: u16) -> Workspace {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": id.to_string(),
+            "monitor": monitor,
+            "windows": windows,
+            "hasfullscreen": false,
+            "lastwindow": "0x0",
+            "lastwindowtitle": "",
+        }))
+        .expect("valid workspace")
+    }
 
-    event_listener.add_keyboard_layout_change_handler(|layout| {
-        println!("{}", layout.keyboard_name.split_once(',').unwrap().1);
-    });
+    /// A monitor as output by `hyprctl monitors -j`
+    fn monitor(name: &str, active_workspace: i32, focused: bool) -> Monitor {
+        serde_json::from_value(json!({
+            "id": 0,
+            "name": name,
+            "description": "",
+            "width": 1920,
+            "height": 1080,
+            "refreshRate": 60.0,
+            "x": 0,
+            "y": 0,
+            "activeWorkspace": { "id": active_workspace, "name": active_workspace.to_string() },
+            "reserved": [0, 0, 0, 0],
+            "scale": 1.0,
+            "transform": 0,
+            "focused": focused,
+            "dpmsStatus": true,
+            "vrr": false,
+        }))
+        .expect("valid monitor")
+    }
 
-    let _ = event_listener.start_listener();
-    Ok(())
+    #[test]
+    fn workspaces_of_monitor() {
+        let workspaces = [
+            workspace(3, "DP-1", 1),
+            workspace(1, "eDP-1", 2),
+            workspace(2, "DP-1", 0),
+            workspace(4, "eDP-1", 5),
+        ];
+        let cases = [
+            (
+                "DP-1",
+                Some(monitor("DP-1", 3, true)),
+                json!({
+                    "focused": true,
+                    "active_workspace": 3,
+                    "workspaces": [{ "id": 2, "windows": 0 }, { "id": 3, "windows": 1 }],
+                }),
+            ),
+            (
+                "eDP-1",
+                Some(monitor("eDP-1", 1, false)),
+                json!({
+                    "focused": false,
+                    "active_workspace": 1,
+                    "workspaces": [{ "id": 1, "windows": 2 }, { "id": 4, "windows": 5 }],
+                }),
+            ),
+            // Disconnected monitor
+            (
+                "HDMI-A-1",
+                None,
+                json!({ "focused": false, "active_workspace": null, "workspaces": [] }),
+            ),
+        ];
+        for (name, monitor, expected) in cases {
+            assert_eq!(
+                monitor_workspaces(name, monitor.as_ref(), &workspaces),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
 }
@@ -1,10 +1,11 @@
 #![allow(dead_code)]
 
-use std::error::Error;
-
-use clap::Subcommand;
+use clap::{builder::TypedValueParser, FromArgMatches, Subcommand};
+mod error;
 mod utils;
 
+use error::Error;
+
 #[derive(Subcommand)]
 enum Commands {
     #[command(subcommand)]
@@ -16,46 +17,243 @@ enum Commands {
     // TODO
     Bluetooth,
     // TODO
+    #[allow(clippy::upper_case_acronyms)]
     MPRIS,
 }
 
 #[derive(Subcommand)]
 enum HyprlandCommand {
-    Workspace,
+    Workspace {
+        /// Only output the workspaces of this monitor
+        #[arg(long)]
+        monitor: Option<String>,
+        /// Output the workspaces of every monitor, keyed by monitor name
+        #[arg(long, conflicts_with = "monitor")]
+        all_monitors: bool,
+    },
     ActiveWindow,
     KeyboardLanguage,
 }
 
 #[derive(Subcommand)]
 enum NetworkCommand {
-    Info,
-    List,
-    Connect,
-    Test,
+    Info {
+        /// Also output DNS, routes and DHCP details
+        #[arg(long)]
+        verbose: bool,
+        #[command(flatten)]
+        probe: network::connectivity::ProbeConfig,
+    },
+    List {
+        /// Merge the access points of a same network, keeping the strongest
+        #[arg(long)]
+        grouped: bool,
+    },
+    /// Scan for access points then output the list
+    Scan {
+        /// Wireless interface to scan with, every wireless device by default
+        #[arg(long)]
+        interface: Option<String>,
+        /// Time to wait for the scans in milliseconds
+        #[arg(long, default_value_t = 10000)]
+        timeout: u64,
+        /// Merge the access points of a same network, keeping the strongest
+        #[arg(long)]
+        grouped: bool,
+    },
+    Connect {
+        #[arg(long)]
+        ssid: String,
+        /// Password of the network, replacing the saved one
+        #[arg(long)]
+        password: Option<String>,
+        /// The network does not broadcast its SSID
+        #[arg(long)]
+        hidden: bool,
+        /// Wireless interface to connect with, the first wireless device by default
+        #[arg(long)]
+        interface: Option<String>,
+    },
+    Vpn {
+        #[command(subcommand)]
+        action: Option<VpnCommand>,
+    },
+    /// Output the state of the cellular modems
+    Modem,
+    /// Output every active connection with its devices and addresses
+    Overview,
+    Latency(network::latency::LatencyConfig),
+    Traffic(network::traffic::TrafficConfig),
+    Wifi {
+        #[arg(value_enum)]
+        action: network::radio::RadioAction,
+    },
+    Networking {
+        #[arg(value_enum)]
+        action: network::radio::RadioAction,
+    },
+    Airplane,
+    Profiles {
+        #[command(subcommand)]
+        action: Option<ProfilesCommand>,
+    },
+    Hotspot {
+        #[command(subcommand)]
+        action: Option<HotspotCommand>,
+    },
+    /// Answer the secret requests of NetworkManager
+    Agent(network::secret_agent::AgentConfig),
+}
+
+#[derive(Subcommand)]
+enum ProfilesCommand {
+    Forget {
+        id: String,
+    },
+    Autoconnect {
+        id: String,
+        #[arg(
+            action = clap::ArgAction::Set,
+            value_parser = clap::builder::PossibleValuesParser::new(["on", "off"])
+                .map(|state| state == "on"),
+        )]
+        state: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HotspotCommand {
+    Start(network::hotspot::HotspotConfig),
+    Stop,
+    Status,
+}
+
+#[derive(Subcommand)]
+enum VpnCommand {
+    Up { name: String },
+    Down { name: String },
 }
 
 mod hyprland;
 mod network;
 
-#[tokio::main]
-async fn main() ->  Result<(), Box<dyn Error>> {
+fn main() {
+    if let Err(error) = run() {
+        // Still a json line, for the eww `deflisten` reading stdout
+        println!("{}", error.to_json());
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
     let cli = clap::Command::new("script");
     let cli = Commands::augment_subcommands(cli);
 
     match cli.get_matches().subcommand() {
         Some(("hyprland", subcommand)) => match subcommand.subcommand() {
-            Some(("workspace",_)) => hyprland::workspaces_listener()?,
+            Some(("workspace", args)) => {
+                let scope = match args.get_one::<String>("monitor") {
+                    Some(monitor) => hyprland::WorkspacesScope::Monitor(monitor.clone()),
+                    None if args.get_flag("all_monitors") => hyprland::WorkspacesScope::PerMonitor,
+                    None => hyprland::WorkspacesScope::All,
+                };
+                hyprland::workspaces_listener(scope)?
+            }
             Some(("active-window",_)) => hyprland::active_window_listener()?,
             Some(("keyboard-language",_)) => hyprland::keyboard_language_listener()?,
             _ => ()
         },
-        Some(("network", subcommand)) => match subcommand.subcommand() {
-            Some(("info",_)) => network::info().await?,
-            Some(("test",_)) => network::test().await?,
-            _ => ()
-        },
+        // The Hyprland listeners are blocking and switch HYPRLAND_INSTANCE_SIGNATURE,
+        // only the network commands run in a tokio runtime
+        Some(("network", subcommand)) => tokio::runtime::Runtime::new()?.block_on(run_network(subcommand))?,
         _ => {}
     }
 
     Ok(())
 }
+
+async fn run_network(subcommand: &clap::ArgMatches) -> Result<(), Error> {
+    match subcommand.subcommand() {
+        Some(("info", args)) => {
+            let probe = network::connectivity::ProbeConfig::from_arg_matches(args)?;
+            network::info(args.get_flag("verbose"), probe).await?
+        }
+        Some(("list", args)) => network::list(args.get_flag("grouped")).await?,
+        Some(("scan", args)) => {
+            let interface = args.get_one::<String>("interface");
+            let timeout = args.get_one::<u64>("timeout").expect("timeout has a default");
+            network::scan(interface.map(String::as_str), *timeout, args.get_flag("grouped")).await?
+        }
+        Some(("connect", args)) => {
+            let ssid = args.get_one::<String>("ssid").expect("ssid is required");
+            let password = args.get_one::<String>("password");
+            let hidden = args.get_flag("hidden");
+            let interface = args.get_one::<String>("interface");
+            network::connect(
+                ssid,
+                password.map(String::as_str),
+                hidden,
+                interface.map(String::as_str),
+            )
+            .await?
+        }
+        Some(("vpn", args)) => match args.subcommand() {
+            Some(("up", args)) => {
+                let name = args.get_one::<String>("name").expect("name is required");
+                network::vpn::up(name).await?
+            }
+            Some(("down", args)) => {
+                let name = args.get_one::<String>("name").expect("name is required");
+                network::vpn::down(name).await?
+            }
+            _ => network::vpn::listener().await?,
+        },
+        Some(("modem",_)) => network::modem::listener().await?,
+        Some(("overview",_)) => network::overview::listener().await?,
+        Some(("latency", args)) => {
+            let config = network::latency::LatencyConfig::from_arg_matches(args)?;
+            network::latency::listener(config).await?
+        }
+        Some(("traffic", args)) => {
+            let config = network::traffic::TrafficConfig::from_arg_matches(args)?;
+            network::traffic::listener(config).await?
+        }
+        Some(("wifi", args)) => {
+            let action = args.get_one::<network::radio::RadioAction>("action");
+            network::radio::wifi(*action.expect("action is required")).await?
+        }
+        Some(("networking", args)) => {
+            let action = args.get_one::<network::radio::RadioAction>("action");
+            network::radio::networking(*action.expect("action is required")).await?
+        }
+        Some(("airplane",_)) => network::radio::airplane().await?,
+        Some(("profiles", args)) => match args.subcommand() {
+            Some(("forget", args)) => {
+                let id = args.get_one::<String>("id").expect("id is required");
+                network::profiles::forget(id).await?
+            }
+            Some(("autoconnect", args)) => {
+                let id = args.get_one::<String>("id").expect("id is required");
+                let state = args.get_one::<bool>("state").expect("state is required");
+                network::profiles::autoconnect(id, *state).await?
+            }
+            _ => network::profiles::listener().await?,
+        },
+        Some(("hotspot", args)) => match args.subcommand() {
+            Some(("start", args)) => {
+                let config = network::hotspot::HotspotConfig::from_arg_matches(args)?;
+                network::hotspot::start(config).await?
+            }
+            Some(("stop",_)) => network::hotspot::stop().await?,
+            _ => network::hotspot::listener().await?,
+        },
+        Some(("agent", args)) => {
+            let config = network::secret_agent::AgentConfig::from_arg_matches(args)?;
+            network::secret_agent::agent(config).await?
+        }
+        _ => ()
+    }
+
+    Ok(())
+}
@@ -1,8 +1,96 @@
-use std::error::Error;
-
 use zbus::{Connection, Proxy};
-use zvariant::OwnedObjectPath;
+use zvariant::{OwnedObjectPath, OwnedValue};
+
+use crate::error::Error;
+
+/// NM80211ApFlags: the access point supports some form of encryption
+const AP_FLAGS_PRIVACY: u32 = 0x1;
+
+/// NM80211ApSecurityFlags
+const AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+const AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+const AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+const AP_SEC_KEY_MGMT_EAP_SUITE_B_192: u32 = 0x2000;
+
+#[derive(Debug, OwnedValue, Clone, Copy, PartialEq, Eq)]
+pub enum NM80211Mode {
+    /// The device or access point mode is unknown
+    Unknown = 0,
+    /// For both devices and access point objects, indicates the object is part of an Ad-Hoc 802.11 network without a central coordinating access point.
+    Adhoc = 1,
+    /// The device or access point is in infrastructure mode
+    Infra = 2,
+    /// The device is an access point/hotspot
+    Ap = 3,
+    /// The device is a 802.11s mesh point
+    Mesh = 4,
+}
+
+impl std::fmt::Display for NM80211Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Security of an access point, as a user would pick it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    /// No password, including Enhanced Open (OWE)
+    Open,
+    Wep,
+    WpaPsk,
+    Wpa2Psk,
+    Wpa3Sae,
+    /// 802.1X authentication, with a user name
+    Enterprise,
+}
+
+impl Security {
+    /// Decode the NM80211ApFlags and the NM80211ApSecurityFlags of an access point
+    pub fn from_flags(flags: u32, wpa_flags: u32, rsn_flags: u32) -> Security {
+        let key_mgmt = wpa_flags | rsn_flags;
+        if key_mgmt & (AP_SEC_KEY_MGMT_802_1X | AP_SEC_KEY_MGMT_EAP_SUITE_B_192) != 0 {
+            Security::Enterprise
+        } else if rsn_flags & AP_SEC_KEY_MGMT_SAE != 0 {
+            // WPA2/WPA3 transition networks accept both, SAE is the safest
+            Security::Wpa3Sae
+        } else if rsn_flags & AP_SEC_KEY_MGMT_PSK != 0 {
+            Security::Wpa2Psk
+        } else if wpa_flags & AP_SEC_KEY_MGMT_PSK != 0 {
+            Security::WpaPsk
+        } else if flags & AP_FLAGS_PRIVACY != 0 && key_mgmt == 0 {
+            Security::Wep
+        } else {
+            Security::Open
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Security::Open => "open",
+            Security::Wep => "wep",
+            Security::WpaPsk => "wpa-psk",
+            Security::Wpa2Psk => "wpa2-psk",
+            Security::Wpa3Sae => "wpa3-sae",
+            Security::Enterprise => "enterprise",
+        }
+    }
+}
+
+/// Decode an SSID, replacing invalid UTF-8 sequences with U+FFFD
+pub fn ssid_to_string(ssid: &[u8]) -> String {
+    String::from_utf8_lossy(ssid).into_owned()
+}
 
+/// Lowercase hexadecimal form of an SSID, which keeps the bytes lost by [`ssid_to_string`]
+pub fn ssid_to_hex(ssid: &[u8]) -> String {
+    ssid.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Hidden networks broadcast an empty SSID, or one made of null bytes
+pub fn is_hidden_ssid(ssid: &[u8]) -> bool {
+    ssid.iter().all(|byte| *byte == 0)
+}
 
 #[derive(Debug)]
 pub struct AccessPoints<'a> {
@@ -15,7 +103,7 @@ impl AccessPoints<'_> {
     pub async fn new<'a>(
         path: OwnedObjectPath,
         connection: &'a Connection,
-    ) -> Result<AccessPoints<'a>, Box<dyn Error>> {
+    ) -> Result<AccessPoints<'a>, Error> {
         let p = Proxy::new(
             connection,
             "org.freedesktop.NetworkManager",
@@ -34,20 +122,127 @@ impl AccessPoints<'_> {
         &self.path
     }
 
-    pub async fn get_ssid(&self) -> Result<String, Box<dyn Error>> {
-        let ssid_raw = self.proxy.get_property::<Vec<u8>>("Ssid").await?;
-        Ok(std::str::from_utf8(&ssid_raw)?.to_string())
+    /// The Service Set Identifier, an arbitrary byte string of up to 32 bytes
+    pub async fn get_ssid_raw(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.proxy.get_property::<Vec<u8>>("Ssid").await?)
+    }
+    /// The SSID decoded as UTF-8, invalid sequences are replaced
+    pub async fn get_ssid(&self) -> Result<String, Error> {
+        Ok(ssid_to_string(&self.get_ssid_raw().await?))
     }
     /// The radio channel frequency in use by the access point, in MHz.
-    pub async fn get_frequency(&self) -> Result<u32, Box<dyn Error>> {
+    pub async fn get_frequency(&self) -> Result<u32, Error> {
         Ok(self.proxy.get_property::<u32>("Frequency").await?)
     }
     /// The maximum bitrate this access point is capable of, in kilobits/second (Kb/s).
-    pub async fn get_max_bitrate(&self) -> Result<u32, Box<dyn Error>> {
+    pub async fn get_max_bitrate(&self) -> Result<u32, Error> {
         Ok(self.proxy.get_property::<u32>("MaxBitrate").await?)
     }
-    pub async fn get_strength(&self) -> Result<u8, Box<dyn Error>> {
+    pub async fn get_strength(&self) -> Result<u8, Error> {
         Ok(self.proxy.get_property::<u8>("Strength").await?)
     }
+    /// Flags describing the capabilities of the access point (NM80211ApFlags).
+    pub async fn get_flags(&self) -> Result<u32, Error> {
+        Ok(self.proxy.get_property::<u32>("Flags").await?)
+    }
+    /// Flags describing the access point's WPA capabilities (NM80211ApSecurityFlags).
+    pub async fn get_wpa_flags(&self) -> Result<u32, Error> {
+        Ok(self.proxy.get_property::<u32>("WpaFlags").await?)
+    }
+    /// Flags describing the access point's RSN (WPA2) capabilities (NM80211ApSecurityFlags).
+    pub async fn get_rsn_flags(&self) -> Result<u32, Error> {
+        Ok(self.proxy.get_property::<u32>("RsnFlags").await?)
+    }
+    /// The hardware address (BSSID) of the access point
+    pub async fn get_hw_address(&self) -> Result<String, Error> {
+        Ok(self.proxy.get_property::<String>("HwAddress").await?)
+    }
+    /// Describes the operating mode of the access point
+    pub async fn get_mode(&self) -> Result<NM80211Mode, Error> {
+        Ok(self.proxy.get_property::<NM80211Mode>("Mode").await?)
+    }
+    /// The timestamp (in CLOCK_BOOTTIME seconds) for the last time the access point was found in scan results.
+    /// A value of -1 means the access point has never been found in scan results.
+    pub async fn get_last_seen(&self) -> Result<i32, Error> {
+        Ok(self.proxy.get_property::<i32>("LastSeen").await?)
+    }
+    pub async fn get_security(&self) -> Result<Security, Error> {
+        let (flags, wpa_flags, rsn_flags) =
+            tokio::try_join!(self.get_flags(), self.get_wpa_flags(), self.get_rsn_flags())?;
+        Ok(Security::from_flags(flags, wpa_flags, rsn_flags))
+    }
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn security_from_flags() {
+        let cases = [
+            (0, 0, 0, Security::Open),
+            (AP_FLAGS_PRIVACY, 0, 0, Security::Wep),
+            (AP_FLAGS_PRIVACY, AP_SEC_KEY_MGMT_PSK, 0, Security::WpaPsk),
+            (AP_FLAGS_PRIVACY, 0, AP_SEC_KEY_MGMT_PSK, Security::Wpa2Psk),
+            (
+                AP_FLAGS_PRIVACY,
+                AP_SEC_KEY_MGMT_PSK,
+                AP_SEC_KEY_MGMT_PSK,
+                Security::Wpa2Psk,
+            ),
+            (AP_FLAGS_PRIVACY, 0, AP_SEC_KEY_MGMT_SAE, Security::Wpa3Sae),
+            // WPA2/WPA3 transition
+            (
+                AP_FLAGS_PRIVACY,
+                0,
+                AP_SEC_KEY_MGMT_PSK | AP_SEC_KEY_MGMT_SAE,
+                Security::Wpa3Sae,
+            ),
+            (
+                AP_FLAGS_PRIVACY,
+                0,
+                AP_SEC_KEY_MGMT_802_1X,
+                Security::Enterprise,
+            ),
+            (
+                AP_FLAGS_PRIVACY,
+                AP_SEC_KEY_MGMT_802_1X,
+                0,
+                Security::Enterprise,
+            ),
+            (
+                AP_FLAGS_PRIVACY,
+                0,
+                AP_SEC_KEY_MGMT_EAP_SUITE_B_192,
+                Security::Enterprise,
+            ),
+        ];
+        for (flags, wpa_flags, rsn_flags, expected) in cases {
+            assert_eq!(
+                Security::from_flags(flags, wpa_flags, rsn_flags),
+                expected,
+                "flags: {:#x}, wpa_flags: {:#x}, rsn_flags: {:#x}",
+                flags,
+                wpa_flags,
+                rsn_flags
+            );
+        }
+    }
+
+    #[test]
+    fn ssid_decoding() {
+        let cases: [(&[u8], &str, &str, bool); 5] = [
+            (b"home", "home", "686f6d65", false),
+            ("café".as_bytes(), "café", "636166c3a9", false),
+            // Latin-1 SSID, invalid UTF-8
+            (b"caf\xe9", "caf\u{fffd}", "636166e9", false),
+            (b"", "", "", true),
+            (b"\0\0\0", "\0\0\0", "000000", true),
+        ];
+        for (ssid, string, hex, hidden) in cases {
+            assert_eq!(ssid_to_string(ssid), string, "{:?}", ssid);
+            assert_eq!(ssid_to_hex(ssid), hex, "{:?}", ssid);
+            assert_eq!(is_hidden_ssid(ssid), hidden, "{:?}", ssid);
+        }
+    }
 }
@@ -1,9 +1,14 @@
-use std::error::Error;
-use zbus::{Connection, Proxy};
+use std::fmt::Display;
+use zbus::{Connection, Proxy, SignalStream};
 use zvariant::{OwnedObjectPath, OwnedValue};
-use super::{device::Device, ip4_config::Ip4Config};
+use super::{
+    device::Device, dhcp4_config::Dhcp4Config, ip4_config::Ip4Config, ip6_config::Ip6Config,
+    settings::SettingsConnection,
+};
+use crate::error::Error;
 
-#[derive(Debug, OwnedValue)]
+#[derive(Debug, OwnedValue, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
 pub enum NMActiveConnectionState {
     UnknownConnection = 0,
     ActivatingConnection = 1,
@@ -12,6 +17,63 @@ pub enum NMActiveConnectionState {
     DeactivatedConnection = 4,
 }
 
+impl Display for NMActiveConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = format!("{:?}", self);
+        write!(f, "{}", state.trim_end_matches("Connection"))
+    }
+}
+
+#[derive(Debug, OwnedValue, Clone, Copy, PartialEq, Eq)]
+pub enum NMActiveConnectionStateReason {
+    /// The reason for the active connection state change is unknown.
+    Unknown = 0,
+    /// No reason was given for the active connection state change.
+    None = 1,
+    /// The active connection changed state because the user disconnected it.
+    UserDisconnected = 2,
+    /// The active connection changed state because the device it was using was disconnected.
+    DeviceDisconnected = 3,
+    /// The service providing the VPN connection was stopped.
+    ServiceStopped = 4,
+    /// The IP config of the active connection was invalid.
+    IpConfigInvalid = 5,
+    /// The connection attempt to the VPN service timed out.
+    ConnectTimeout = 6,
+    /// A timeout occurred while starting the service providing the VPN connection.
+    ServiceStartTimeout = 7,
+    /// Starting the service providing the VPN connection failed.
+    ServiceStartFailed = 8,
+    /// Necessary secrets for the connection were not provided.
+    NoSecrets = 9,
+    /// Authentication to the server failed.
+    LoginFailed = 10,
+    /// The connection was deleted from settings.
+    ConnectionRemoved = 11,
+    /// Master connection of this connection failed to activate.
+    DependencyFailed = 12,
+    /// Could not create the software device link.
+    DeviceRealizeFailed = 13,
+    /// The device this connection depended on disappeared.
+    DeviceRemoved = 14,
+}
+
+impl NMActiveConnectionStateReason {
+    /// Whether the connection failed because of missing or wrong credentials
+    pub fn is_auth_failure(&self) -> bool {
+        matches!(
+            self,
+            NMActiveConnectionStateReason::NoSecrets | NMActiveConnectionStateReason::LoginFailed
+        )
+    }
+}
+
+impl Display for NMActiveConnectionStateReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 pub struct ActiveConnection<'a> {
     path: String,
     proxy: Proxy<'a>,
@@ -22,7 +84,7 @@ impl ActiveConnection<'_> {
     pub async fn new<'a>(
         path: OwnedObjectPath,
         connection: &'a Connection,
-    ) -> Result<ActiveConnection<'a>, Box<dyn Error>> {
+    ) -> Result<ActiveConnection<'a>, Error> {
         let p = Proxy::new(
             connection,
             "org.freedesktop.NetworkManager",
@@ -41,46 +103,97 @@ impl ActiveConnection<'_> {
         &self.path
     }
 
-    pub async fn get_id(&self) -> Result<String, Box<dyn Error>> {
+    pub async fn get_id(&self) -> Result<String, Error> {
         Ok(self.proxy.get_property::<String>("Id").await?)
     }
 
-    pub async fn get_uuid(&self) -> Result<String, Box<dyn Error>> {
+    pub async fn get_uuid(&self) -> Result<String, Error> {
         Ok(self.proxy.get_property::<String>("Uuid").await?)
     }
 
-    pub async fn get_type(&self) -> Result<String, Box<dyn Error>> {
+    pub async fn get_type(&self) -> Result<String, Error> {
         Ok(self.proxy.get_property::<String>("Type").await?)
     }
 
-    pub async fn is_vpn(&self) -> Result<bool, Box<dyn Error>> {
+    pub async fn is_vpn(&self) -> Result<bool, Error> {
         Ok(self.proxy.get_property::<bool>("Vpn").await?)
     }
 
-    pub async fn get_devices(&self) -> Result<Vec<Device>, Box<dyn Error>> {
+    /// Whether this connection owns the default IPv4 route
+    pub async fn is_default(&self) -> Result<bool, Error> {
+        Ok(self.proxy.get_property::<bool>("Default").await?)
+    }
+
+    /// Whether this connection owns the default IPv6 route
+    pub async fn is_default6(&self) -> Result<bool, Error> {
+        Ok(self.proxy.get_property::<bool>("Default6").await?)
+    }
+
+    pub async fn get_devices(&self) -> Result<Vec<Device<'_>>, Error> {
         let devices_path = self
             .proxy
             .get_property::<Vec<OwnedObjectPath>>("Devices")
             .await?;
         let mut devices = vec![];
         for device_path in devices_path {
-            devices.push(Device::new(device_path, &self.connection).await?)
+            devices.push(Device::new(device_path, self.connection).await?)
         }
         Ok(devices)
     }
 
-    pub async fn get_state(&self) -> Result<NMActiveConnectionState, Box<dyn Error>> {
+    pub async fn get_state(&self) -> Result<NMActiveConnectionState, Error> {
         Ok(self
             .proxy
             .get_property::<NMActiveConnectionState>("State")
             .await?)
     }
 
-    pub async fn get_ip4_config(&self) -> Result<Ip4Config, Box<dyn Error>> {
+    /// The saved profile this connection was activated from
+    pub async fn get_settings_connection(&self) -> Result<SettingsConnection<'_>, Error> {
+        let path = self
+            .proxy
+            .get_property::<OwnedObjectPath>("Connection")
+            .await?;
+        SettingsConnection::new(path, self.connection).await
+    }
+
+    /// Stream of `(state, reason)` emitted whenever the state of the connection changes
+    pub async fn receive_state_changed(&self) -> Result<SignalStream<'static>, Error> {
+        Ok(self.proxy.receive_signal("StateChanged").await?)
+    }
+
+    pub async fn get_ip4_config(&self) -> Result<Ip4Config<'_>, Error> {
         let path = self
             .proxy
             .get_property::<OwnedObjectPath>("Ip4Config")
             .await?;
-        Ok(Ip4Config::new(path, self.connection).await?)
+        Ip4Config::new(path, self.connection).await
     }
+
+    pub async fn get_dhcp4_config(&self) -> Result<Dhcp4Config<'_>, Error> {
+        let path = self
+            .proxy
+            .get_property::<OwnedObjectPath>("Dhcp4Config")
+            .await?;
+        Dhcp4Config::new(path, self.connection).await
+    }
+
+    pub async fn get_ip6_config(&self) -> Result<Ip6Config<'_>, Error> {
+        let path = self
+            .proxy
+            .get_property::<OwnedObjectPath>("Ip6Config")
+            .await?;
+        Ip6Config::new(path, self.connection).await
+    }
+}
+
+/// Decode the body of a `StateChanged` signal of "org.freedesktop.NetworkManager.Connection.Active"
+pub fn parse_state_changed(
+    msg: &zbus::Message,
+) -> Result<(NMActiveConnectionState, NMActiveConnectionStateReason), Error> {
+    let (state, reason) = msg.body::<(u32, u32)>()?;
+    Ok((
+        OwnedValue::from(state).try_into()?,
+        OwnedValue::from(reason).try_into()?,
+    ))
 }
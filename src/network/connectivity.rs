@@ -0,0 +1,156 @@
+use std::time::{Duration, Instant};
+
+use clap::{Args, ValueEnum};
+
+use super::{networkmanager::NetworkManager, ping};
+use crate::error::Error;
+
+/// How the access to internet is checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProbeKind {
+    /// ICMP echo request, needs the right to open ICMP sockets
+    Icmp,
+    /// TCP connection to `host:port`
+    Tcp,
+    /// HTTP(S) GET on an URL, optionally checking the response body
+    Http,
+    /// Connectivity state computed by NetworkManager itself
+    #[value(name = "nm")]
+    NetworkManager,
+}
+
+impl ProbeKind {
+    fn default_hosts(&self) -> &'static [&'static str] {
+        match self {
+            ProbeKind::Icmp => &["1.1.1.1"],
+            ProbeKind::Tcp => &["1.1.1.1:443"],
+            ProbeKind::Http => &["http://nmcheck.gnome.org/check_network_status.txt"],
+            ProbeKind::NetworkManager => &[],
+        }
+    }
+
+    /// Text the body of the default hosts answer with when online
+    fn default_expected_body(&self) -> Option<&'static str> {
+        match self {
+            ProbeKind::Http => Some("NetworkManager is online"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ProbeConfig {
+    /// How to check the access to internet
+    #[arg(long = "probe", value_enum, default_value_t = ProbeKind::NetworkManager)]
+    pub kind: ProbeKind,
+    /// Host to probe, tried in order: a host for icmp, host:port for tcp, an URL for http
+    #[arg(long = "probe-host")]
+    pub hosts: Vec<String>,
+    /// Timeout of one probe in milliseconds
+    #[arg(long = "probe-timeout", default_value_t = 2000)]
+    pub timeout_ms: u64,
+    /// Number of retries after a failed probe
+    #[arg(long = "probe-retries", default_value_t = 1)]
+    pub retries: u32,
+    /// Text the body of the http response must contain,
+    /// "NetworkManager is online" for the default host
+    #[arg(long = "probe-expect")]
+    pub expected_body: Option<String>,
+    /// Time between two probes in milliseconds, the state changes of NetworkManager
+    /// trigger a probe too
+    #[arg(
+        long = "probe-interval",
+        default_value_t = 30000,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub interval_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProbeResult {
+    pub connected: bool,
+    /// Round trip of the successful probe, `None` when unreachable or not measured
+    pub latency: Option<Duration>,
+}
+
+impl ProbeResult {
+    pub fn latency_ms(&self) -> Option<f64> {
+        self.latency.map(|latency| latency.as_secs_f64() * 1000.0)
+    }
+}
+
+/// Check the access to internet as described by `config`
+pub async fn probe(config: &ProbeConfig, nm: &NetworkManager<'_>) -> ProbeResult {
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let hosts = match config.hosts.is_empty() {
+        true => config
+            .kind
+            .default_hosts()
+            .iter()
+            .map(|host| host.to_string())
+            .collect(),
+        false => config.hosts.clone(),
+    };
+    // A captive portal answer the default host too, only its own body tells
+    let expected_body = match (&config.expected_body, config.hosts.is_empty()) {
+        (Some(expected_body), _) => Some(expected_body.as_str()),
+        (None, true) => config.kind.default_expected_body(),
+        (None, false) => None,
+    };
+
+    for attempt in 0..=config.retries {
+        if config.kind == ProbeKind::NetworkManager {
+            // Only ask for a new check when the cached state is not good enough
+            let check = nm.check_connectivity(attempt > 0);
+            if let Ok(Ok(true)) = tokio::time::timeout(timeout, check).await {
+                return ProbeResult {
+                    connected: true,
+                    latency: None,
+                };
+            }
+            continue;
+        }
+        for host in &hosts {
+            let start = Instant::now();
+            let probe = probe_host(config.kind, host, expected_body);
+            if let Ok(Ok(latency)) = tokio::time::timeout(timeout, probe).await {
+                return ProbeResult {
+                    connected: true,
+                    latency: Some(latency.unwrap_or_else(|| start.elapsed())),
+                };
+            }
+        }
+    }
+    ProbeResult {
+        connected: false,
+        latency: None,
+    }
+}
+
+/// Probe one host, return the measured round trip when the probe measure it itself
+async fn probe_host(
+    kind: ProbeKind,
+    host: &str,
+    expected_body: Option<&str>,
+) -> Result<Option<Duration>, Error> {
+    match kind {
+        ProbeKind::Icmp => {
+            let (_, rtt) = ping::ping(host).await?;
+            Ok(Some(rtt))
+        }
+        ProbeKind::Tcp => {
+            tokio::net::TcpStream::connect(host).await?;
+            Ok(None)
+        }
+        ProbeKind::Http => {
+            let response = reqwest::get(host).await?.error_for_status()?;
+            if let Some(expected_body) = expected_body {
+                if !response.text().await?.contains(expected_body) {
+                    return Err("unexpected http response body".into());
+                }
+            }
+            Ok(None)
+        }
+        ProbeKind::NetworkManager => Err("NetworkManager is not probed per host".into()),
+    }
+}
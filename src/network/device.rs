@@ -1,8 +1,10 @@
-use std::{error::Error, fmt::Debug};
-use zbus::{Connection, Proxy};
-use zvariant::{OwnedObjectPath, OwnedValue};
+use std::{collections::HashMap, fmt::Debug};
+
+use zbus::{Connection, PropertyStream, Proxy, SignalStream};
+use zvariant::{OwnedObjectPath, OwnedValue, Value};
 
 use super::access_point::AccessPoints;
+use crate::error::Error;
 
 #[derive(Debug, OwnedValue)]
 pub enum NMDeviceState {
@@ -46,8 +48,8 @@ pub enum NMDeviceState {
     Failed = 120,
 }
 
-#[derive(Debug, OwnedValue)]
-enum NMDeviceType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NMDeviceType {
     /// unknown device
     Unknown = 0,
     /// a wired ethernet device
@@ -88,6 +90,118 @@ enum NMDeviceType {
     Vxlan = 19,
     /// a VETH interface
     Veth = 20,
+    /// a MACsec interface
+    Macsec = 21,
+    /// a dummy interface
+    Dummy = 22,
+    /// a PPP interface
+    Ppp = 23,
+    /// a Open vSwitch interface
+    OvsInterface = 24,
+    /// a Open vSwitch port
+    OvsPort = 25,
+    /// a Open vSwitch bridge
+    OvsBridge = 26,
+    /// a IEEE 802.15.4 (WPAN) MAC Layer Device
+    Wpan = 27,
+    /// 6LoWPAN interface
+    SixLowpan = 28,
+    /// a WireGuard interface
+    Wireguard = 29,
+    /// an 802.11 Wi-Fi P2P device
+    WifiP2p = 30,
+    /// A VRF (Virtual Routing and Forwarding) interface
+    Vrf = 31,
+    /// a loopback interface
+    Loopback = 32,
+    /// a HSR/PRP device
+    Hsr = 33,
+    /// a IPVLAN device
+    Ipvlan = 34,
+}
+
+impl From<u32> for NMDeviceType {
+    /// Device types added to NetworkManager after this list are [`NMDeviceType::Unknown`]
+    fn from(device_type: u32) -> NMDeviceType {
+        match device_type {
+            1 => NMDeviceType::Ethernet,
+            2 => NMDeviceType::Wifi,
+            3 => NMDeviceType::Unused1,
+            4 => NMDeviceType::Unused2,
+            5 => NMDeviceType::Bt,
+            6 => NMDeviceType::OlpcMesh,
+            7 => NMDeviceType::Wimax,
+            8 => NMDeviceType::Modem,
+            9 => NMDeviceType::Infiniband,
+            10 => NMDeviceType::Bond,
+            11 => NMDeviceType::Vlan,
+            12 => NMDeviceType::Adsl,
+            13 => NMDeviceType::Bridge,
+            14 => NMDeviceType::Generic,
+            15 => NMDeviceType::Team,
+            16 => NMDeviceType::Tun,
+            17 => NMDeviceType::IpTunnel,
+            18 => NMDeviceType::Macvlan,
+            19 => NMDeviceType::Vxlan,
+            20 => NMDeviceType::Veth,
+            21 => NMDeviceType::Macsec,
+            22 => NMDeviceType::Dummy,
+            23 => NMDeviceType::Ppp,
+            24 => NMDeviceType::OvsInterface,
+            25 => NMDeviceType::OvsPort,
+            26 => NMDeviceType::OvsBridge,
+            27 => NMDeviceType::Wpan,
+            28 => NMDeviceType::SixLowpan,
+            29 => NMDeviceType::Wireguard,
+            30 => NMDeviceType::WifiP2p,
+            31 => NMDeviceType::Vrf,
+            32 => NMDeviceType::Loopback,
+            33 => NMDeviceType::Hsr,
+            34 => NMDeviceType::Ipvlan,
+            _ => NMDeviceType::Unknown,
+        }
+    }
+}
+
+impl NMDeviceType {
+    /// Short name of the device type, as shown by nmcli
+    pub fn name(&self) -> &'static str {
+        match self {
+            NMDeviceType::Unknown | NMDeviceType::Unused1 | NMDeviceType::Unused2 => "unknown",
+            NMDeviceType::Ethernet => "ethernet",
+            NMDeviceType::Wifi => "wifi",
+            NMDeviceType::Bt => "bt",
+            NMDeviceType::OlpcMesh => "olpc-mesh",
+            NMDeviceType::Wimax => "wimax",
+            NMDeviceType::Modem => "gsm",
+            NMDeviceType::Infiniband => "infiniband",
+            NMDeviceType::Bond => "bond",
+            NMDeviceType::Vlan => "vlan",
+            NMDeviceType::Adsl => "adsl",
+            NMDeviceType::Bridge => "bridge",
+            NMDeviceType::Generic => "generic",
+            NMDeviceType::Team => "team",
+            NMDeviceType::Tun => "tun",
+            NMDeviceType::IpTunnel => "ip-tunnel",
+            NMDeviceType::Macvlan => "macvlan",
+            NMDeviceType::Vxlan => "vxlan",
+            NMDeviceType::Veth => "veth",
+            NMDeviceType::Macsec => "macsec",
+            NMDeviceType::Dummy => "dummy",
+            NMDeviceType::Ppp => "ppp",
+            NMDeviceType::OvsInterface => "ovs-interface",
+            NMDeviceType::OvsPort => "ovs-port",
+            NMDeviceType::OvsBridge => "ovs-bridge",
+            NMDeviceType::Wpan => "wpan",
+            NMDeviceType::SixLowpan => "6lowpan",
+            NMDeviceType::Wireguard => "wireguard",
+            NMDeviceType::WifiP2p => "wifi-p2p",
+            NMDeviceType::Vrf => "vrf",
+            NMDeviceType::Loopback => "loopback",
+            NMDeviceType::Hsr => "hsr",
+            NMDeviceType::Ipvlan => "ipvlan",
+        }
+    }
 }
 
 pub struct WirelessDevice<'a> {
@@ -112,7 +226,7 @@ impl WirelessDevice<'_> {
     pub async fn new<'a>(
         path: OwnedObjectPath,
         connection: &'a Connection,
-    ) -> Result<WirelessDevice<'a>, Box<dyn Error>> {
+    ) -> Result<WirelessDevice<'a>, Error> {
         let proxy_generic = Proxy::new(
             connection,
             "org.freedesktop.NetworkManager",
@@ -135,7 +249,7 @@ impl WirelessDevice<'_> {
         })
     }
 
-    fn path(&self) -> &String {
+    pub fn path(&self) -> &String {
         &self.path
     }
 
@@ -143,11 +257,11 @@ impl WirelessDevice<'_> {
         &self.proxy_generic
     }
 
-    pub async fn get_interface(&self) -> Result<String, Box<dyn Error>> {
+    pub async fn get_interface(&self) -> Result<String, Error> {
         Ok(self.proxy().get_property::<String>("Interface").await?)
     }
 
-    pub async fn get_state(&self) -> Result<NMDeviceState, Box<dyn Error>> {
+    pub async fn get_state(&self) -> Result<NMDeviceState, Error> {
         Ok(self.proxy().get_property::<NMDeviceState>("State").await?)
     }
 }
@@ -157,34 +271,69 @@ impl WirelessDevice<'_> {
         &self.proxy_device
     }
 
-    pub async fn get_hw_address(&self) -> Result<String, Box<dyn Error>> {
+    pub async fn get_hw_address(&self) -> Result<String, Error> {
         Ok(self.proxy().get_property::<String>("HwAddress").await?)
     }
-    pub async fn get_active_access_point(&self) -> Result<AccessPoints, Box<dyn Error>> {
+    /// Capabilities of the wireless device (NMDeviceWifiCapabilities)
+    pub async fn get_wireless_capabilities(&self) -> Result<u32, Error> {
+        Ok(self
+            .proxy_device()
+            .get_property::<u32>("WirelessCapabilities")
+            .await?)
+    }
+    pub async fn get_active_access_point(&self) -> Result<AccessPoints<'_>, Error> {
         let access_point_path = self
             .proxy_device()
             .get_property::<OwnedObjectPath>("ActiveAccessPoint")
             .await?;
-        Ok(AccessPoints::new(access_point_path, &self.connection).await?)
+        AccessPoints::new(access_point_path, self.connection).await
     }
-    pub async fn get_access_points(&self) -> Result<Vec<AccessPoints>, Box<dyn Error>> {
+    pub async fn get_access_points(&self) -> Result<Vec<AccessPoints<'_>>, Error> {
         let access_points_path = self
             .proxy_device()
             .get_property::<Vec<OwnedObjectPath>>("AccessPoints")
             .await?;
         let mut access_points = vec![];
         for access_point_path in access_points_path {
-            access_points.push(AccessPoints::new(access_point_path, &self.connection).await?)
+            access_points.push(AccessPoints::new(access_point_path, self.connection).await?)
         }
         Ok(access_points)
     }
+    pub async fn receive_access_point_added(&self) -> Result<SignalStream<'static>, Error> {
+        Ok(self.proxy_device().receive_signal("AccessPointAdded").await?)
+    }
+    pub async fn receive_access_point_removed(
+        &self,
+    ) -> Result<SignalStream<'static>, Error> {
+        Ok(self.proxy_device().receive_signal("AccessPointRemoved").await?)
+    }
+    pub async fn receive_active_access_point_changed(&self) -> PropertyStream<'_, OwnedObjectPath> {
+        self.proxy_device()
+            .receive_property_changed("ActiveAccessPoint")
+            .await
+    }
+    /// Ask the device to scan for access points, NetworkManager refuses it right after a scan
+    pub async fn request_scan(&self) -> Result<(), Error> {
+        let options: HashMap<&str, Value> = HashMap::new();
+        self.proxy_device()
+            .call::<_, _, ()>("RequestScan", &options)
+            .await?;
+        Ok(())
+    }
+    /// Milliseconds since CLOCK_BOOTTIME of the last scan, -1 when the device never scanned
+    pub async fn get_last_scan(&self) -> Result<i64, Error> {
+        Ok(self.proxy_device().get_property::<i64>("LastScan").await?)
+    }
+    pub async fn receive_last_scan_changed(&self) -> PropertyStream<'_, i64> {
+        self.proxy_device().receive_property_changed("LastScan").await
+    }
 }
 
 impl WiredDevice<'_> {
     pub async fn new<'a>(
         path: OwnedObjectPath,
         connection: &'a Connection,
-    ) -> Result<WiredDevice<'a>, Box<dyn Error>> {
+    ) -> Result<WiredDevice<'a>, Error> {
         let proxy_generic = Proxy::new(
             connection,
             "org.freedesktop.NetworkManager",
@@ -214,33 +363,212 @@ impl WiredDevice<'_> {
         &self.proxy_generic
     }
 
-    pub async fn get_interface(&self) -> Result<String, Box<dyn Error>> {
+    pub async fn get_interface(&self) -> Result<String, Error> {
         Ok(self.proxy().get_property::<String>("Interface").await?)
     }
 
-    pub async fn get_state(&self) -> Result<NMDeviceState, Box<dyn Error>> {
+    pub async fn get_state(&self) -> Result<NMDeviceState, Error> {
         Ok(self.proxy().get_property::<NMDeviceState>("State").await?)
     }
 }
 
+pub struct BluetoothDevice<'a> {
+    path: String,
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Device.Bluetooth"
+    proxy_device: Proxy<'a>,
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Device"
+    proxy_generic: Proxy<'a>,
+    connection: &'a Connection,
+}
+
+pub struct ModemDevice<'a> {
+    path: String,
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Device.Modem"
+    proxy_device: Proxy<'a>,
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Device"
+    proxy_generic: Proxy<'a>,
+    connection: &'a Connection,
+}
+
+pub struct WireGuardDevice<'a> {
+    path: String,
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Device.WireGuard"
+    proxy_device: Proxy<'a>,
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Device"
+    proxy_generic: Proxy<'a>,
+    connection: &'a Connection,
+}
+
+/// Any device without a dedicated wrapper (bridge, tun, vlan, loopback, ...)
+pub struct GenericDevice<'a> {
+    path: String,
+    device_type: NMDeviceType,
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Device"
+    proxy_generic: Proxy<'a>,
+    connection: &'a Connection,
+}
+
+async fn new_proxy<'a>(
+    path: &OwnedObjectPath,
+    connection: &'a Connection,
+    interface: &'static str,
+) -> Result<Proxy<'a>, Error> {
+    Ok(Proxy::new(
+        connection,
+        "org.freedesktop.NetworkManager",
+        path.clone(),
+        interface,
+    )
+    .await?)
+}
+
+impl BluetoothDevice<'_> {
+    pub async fn new<'a>(
+        path: OwnedObjectPath,
+        connection: &'a Connection,
+    ) -> Result<BluetoothDevice<'a>, Error> {
+        Ok(BluetoothDevice {
+            proxy_generic: new_proxy(&path, connection, "org.freedesktop.NetworkManager.Device")
+                .await?,
+            proxy_device: new_proxy(
+                &path,
+                connection,
+                "org.freedesktop.NetworkManager.Device.Bluetooth",
+            )
+            .await?,
+            path: path.to_string(),
+            connection,
+        })
+    }
+
+    /// Name of the remote Bluetooth device
+    pub async fn get_name(&self) -> Result<String, Error> {
+        Ok(self.proxy_device.get_property::<String>("Name").await?)
+    }
+    /// Capabilities of the remote Bluetooth device (NMBluetoothCapabilities: 1 DUN, 2 PAN)
+    pub async fn get_bt_capabilities(&self) -> Result<u32, Error> {
+        Ok(self.proxy_device.get_property::<u32>("BtCapabilities").await?)
+    }
+}
+
+impl ModemDevice<'_> {
+    pub async fn new<'a>(
+        path: OwnedObjectPath,
+        connection: &'a Connection,
+    ) -> Result<ModemDevice<'a>, Error> {
+        Ok(ModemDevice {
+            proxy_generic: new_proxy(&path, connection, "org.freedesktop.NetworkManager.Device")
+                .await?,
+            proxy_device: new_proxy(
+                &path,
+                connection,
+                "org.freedesktop.NetworkManager.Device.Modem",
+            )
+            .await?,
+            path: path.to_string(),
+            connection,
+        })
+    }
+
+    /// Generic family of access technologies the modem supports (NMDeviceModemCapabilities)
+    pub async fn get_modem_capabilities(&self) -> Result<u32, Error> {
+        Ok(self.proxy_device.get_property::<u32>("ModemCapabilities").await?)
+    }
+    /// Access technologies the modem currently supports without a firmware reload or reinitialization
+    pub async fn get_current_capabilities(&self) -> Result<u32, Error> {
+        Ok(self.proxy_device.get_property::<u32>("CurrentCapabilities").await?)
+    }
+    /// Identifier used by the modem backend (ModemManager) to refer to the modem
+    pub async fn get_device_id(&self) -> Result<String, Error> {
+        Ok(self.proxy_device.get_property::<String>("DeviceId").await?)
+    }
+    /// MCC-MNC of the network the modem is registered to
+    pub async fn get_operator_code(&self) -> Result<String, Error> {
+        Ok(self.proxy_device.get_property::<String>("OperatorCode").await?)
+    }
+    /// Access Point Name the modem is connected to
+    pub async fn get_apn(&self) -> Result<String, Error> {
+        Ok(self.proxy_device.get_property::<String>("Apn").await?)
+    }
+}
+
+impl WireGuardDevice<'_> {
+    pub async fn new<'a>(
+        path: OwnedObjectPath,
+        connection: &'a Connection,
+    ) -> Result<WireGuardDevice<'a>, Error> {
+        Ok(WireGuardDevice {
+            proxy_generic: new_proxy(&path, connection, "org.freedesktop.NetworkManager.Device")
+                .await?,
+            proxy_device: new_proxy(
+                &path,
+                connection,
+                "org.freedesktop.NetworkManager.Device.WireGuard",
+            )
+            .await?,
+            path: path.to_string(),
+            connection,
+        })
+    }
+
+    /// Public key of the interface, raw 32 bytes
+    pub async fn get_public_key(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.proxy_device.get_property::<Vec<u8>>("PublicKey").await?)
+    }
+    /// Local UDP port used by the interface, 0 when picked randomly
+    pub async fn get_listen_port(&self) -> Result<u16, Error> {
+        Ok(self.proxy_device.get_property::<u16>("ListenPort").await?)
+    }
+    /// Optional firewall mark applied to outgoing packets
+    pub async fn get_fw_mark(&self) -> Result<u32, Error> {
+        Ok(self.proxy_device.get_property::<u32>("FwMark").await?)
+    }
+}
+
+impl GenericDevice<'_> {
+    pub async fn new<'a>(
+        path: OwnedObjectPath,
+        device_type: NMDeviceType,
+        connection: &'a Connection,
+    ) -> Result<GenericDevice<'a>, Error> {
+        Ok(GenericDevice {
+            proxy_generic: new_proxy(&path, connection, "org.freedesktop.NetworkManager.Device")
+                .await?,
+            path: path.to_string(),
+            device_type,
+            connection,
+        })
+    }
+
+    pub fn get_device_type(&self) -> NMDeviceType {
+        self.device_type
+    }
+}
+
+pub async fn get_device_type(
+    path: &OwnedObjectPath,
+    connection: &Connection,
+) -> Result<NMDeviceType, Error> {
+    let p = new_proxy(path, connection, "org.freedesktop.NetworkManager.Device").await?;
+    Ok(p.get_property::<u32>("DeviceType").await?.into())
+}
+
+#[allow(clippy::enum_variant_names)]
 pub enum Device<'a> {
     WirelessDevice(WirelessDevice<'a>),
     WiredDevice(WiredDevice<'a>),
+    BluetoothDevice(BluetoothDevice<'a>),
+    ModemDevice(ModemDevice<'a>),
+    WireGuardDevice(WireGuardDevice<'a>),
+    GenericDevice(GenericDevice<'a>),
 }
 
 impl Device<'_> {
     pub async fn new<'a>(
         path: OwnedObjectPath,
         connection: &'a Connection,
-    ) -> Result<Device<'a>, Box<dyn Error>> {
-        let p = Proxy::new(
-            connection,
-            "org.freedesktop.NetworkManager",
-            path.clone(),
-            "org.freedesktop.NetworkManager.Device",
-        )
-        .await?;
-        let device_type = p.get_property::<NMDeviceType>("DeviceType").await?;
+    ) -> Result<Device<'a>, Error> {
+        let device_type = get_device_type(&path, connection).await?;
 
         let device = match device_type {
             NMDeviceType::Wifi => {
@@ -249,44 +577,142 @@ impl Device<'_> {
             NMDeviceType::Ethernet => {
                 Device::WiredDevice(WiredDevice::new(path, connection).await?)
             }
-            _ => todo!(),
+            NMDeviceType::Bt => {
+                Device::BluetoothDevice(BluetoothDevice::new(path, connection).await?)
+            }
+            NMDeviceType::Modem => Device::ModemDevice(ModemDevice::new(path, connection).await?),
+            NMDeviceType::Wireguard => {
+                Device::WireGuardDevice(WireGuardDevice::new(path, connection).await?)
+            }
+            _ => Device::GenericDevice(GenericDevice::new(path, device_type, connection).await?),
         };
         Ok(device)
     }
 
     pub fn path(&self) -> &String {
         match self {
-            Device::WirelessDevice(x) => x.path(),
-            Device::WiredDevice(x) => x.path(),
+            Device::WirelessDevice(x) => &x.path,
+            Device::WiredDevice(x) => &x.path,
+            Device::BluetoothDevice(x) => &x.path,
+            Device::ModemDevice(x) => &x.path,
+            Device::WireGuardDevice(x) => &x.path,
+            Device::GenericDevice(x) => &x.path,
         }
     }
 
-    pub async fn get_state(&self) -> Result<NMDeviceState, Box<dyn Error>> {
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Device"
+    fn proxy(&self) -> &Proxy<'_> {
         match self {
-            Device::WirelessDevice(x) => x.get_state().await,
-            Device::WiredDevice(x) => x.get_state().await,
+            Device::WirelessDevice(x) => &x.proxy_generic,
+            Device::WiredDevice(x) => &x.proxy_generic,
+            Device::BluetoothDevice(x) => &x.proxy_generic,
+            Device::ModemDevice(x) => &x.proxy_generic,
+            Device::WireGuardDevice(x) => &x.proxy_generic,
+            Device::GenericDevice(x) => &x.proxy_generic,
         }
     }
 
-    pub async fn get_interface(&self) -> Result<String, Box<dyn Error>> {
+    pub fn connection(&self) -> &Connection {
         match self {
-            Device::WirelessDevice(x) => x.get_interface().await,
-            Device::WiredDevice(x) => x.get_interface().await,
+            Device::WirelessDevice(x) => x.connection,
+            Device::WiredDevice(x) => x.connection,
+            Device::BluetoothDevice(x) => x.connection,
+            Device::ModemDevice(x) => x.connection,
+            Device::WireGuardDevice(x) => x.connection,
+            Device::GenericDevice(x) => x.connection,
         }
     }
 
-    pub async fn get_device_data<'a>(&self) -> Result<(&'a str, u32, u8, String), Box<dyn Error>> {
+    pub async fn get_state(&self) -> Result<NMDeviceState, Error> {
+        Ok(self.proxy().get_property::<NMDeviceState>("State").await?)
+    }
+
+    pub async fn get_interface(&self) -> Result<String, Error> {
+        Ok(self.proxy().get_property::<String>("Interface").await?)
+    }
+
+    pub async fn get_device_type(&self) -> Result<NMDeviceType, Error> {
+        Ok(self.proxy().get_property::<u32>("DeviceType").await?.into())
+    }
+
+    /// Operating-system specific transient device hardware identifier
+    pub async fn get_udi(&self) -> Result<String, Error> {
+        Ok(self.proxy().get_property::<String>("Udi").await?)
+    }
+
+    /// The name of the device's data interface when available
+    pub async fn get_ip_interface(&self) -> Result<String, Error> {
+        Ok(self.proxy().get_property::<String>("IpInterface").await?)
+    }
+
+    /// The driver handling the device
+    pub async fn get_driver(&self) -> Result<String, Error> {
+        Ok(self.proxy().get_property::<String>("Driver").await?)
+    }
+
+    pub async fn get_driver_version(&self) -> Result<String, Error> {
+        Ok(self.proxy().get_property::<String>("DriverVersion").await?)
+    }
+
+    pub async fn get_firmware_version(&self) -> Result<String, Error> {
+        Ok(self.proxy().get_property::<String>("FirmwareVersion").await?)
+    }
+
+    /// Flags describing the capabilities of the device (NMDeviceCapabilities)
+    pub async fn get_capabilities(&self) -> Result<u32, Error> {
+        Ok(self.proxy().get_property::<u32>("Capabilities").await?)
+    }
+
+    /// Whether or not this device is managed by NetworkManager
+    pub async fn is_managed(&self) -> Result<bool, Error> {
+        Ok(self.proxy().get_property::<bool>("Managed").await?)
+    }
+
+    /// If true, the device is allowed to autoconnect
+    pub async fn is_autoconnect(&self) -> Result<bool, Error> {
+        Ok(self.proxy().get_property::<bool>("Autoconnect").await?)
+    }
+
+    /// False for software devices that do not exist yet
+    pub async fn is_real(&self) -> Result<bool, Error> {
+        Ok(self.proxy().get_property::<bool>("Real").await?)
+    }
+
+    /// The device MTU (maximum transmission unit)
+    pub async fn get_mtu(&self) -> Result<u32, Error> {
+        Ok(self.proxy().get_property::<u32>("Mtu").await?)
+    }
+
+    /// The hardware address of the device, empty for devices without one
+    pub async fn get_hw_address(&self) -> Result<String, Error> {
+        Ok(self.proxy().get_property::<String>("HwAddress").await?)
+    }
+
+    /// Object path of the active connection using this device, "/" when there is none
+    pub async fn get_active_connection_path(&self) -> Result<OwnedObjectPath, Error> {
+        Ok(self
+            .proxy()
+            .get_property::<OwnedObjectPath>("ActiveConnection")
+            .await?)
+    }
+
+    /// Kind, frequency, signal strength and raw SSID of the device, the last three are only set for wireless devices
+    pub async fn get_device_data<'a>(&self) -> Result<(&'a str, u32, u8, Vec<u8>), Error> {
         let (kind, frequency, signal_strength, ssid) = match self {
             Device::WirelessDevice(x) => {
                 let access_point = x.get_active_access_point().await?;
     
                 let frequency = access_point.get_frequency().await?;
                 let signal_strength = access_point.get_strength().await?;
-                let ssid = access_point.get_ssid().await?;
+                let ssid = access_point.get_ssid_raw().await?;
     
                 ("wireless", frequency, signal_strength, ssid)
             }
-            Device::WiredDevice(_) => ("wired",0, 0, "".to_string()),
+            Device::WiredDevice(_) => ("wired",0, 0, vec![]),
+            Device::BluetoothDevice(_) => ("bluetooth", 0, 0, vec![]),
+            Device::ModemDevice(_) => ("modem", 0, 0, vec![]),
+            Device::WireGuardDevice(_) => ("wireguard", 0, 0, vec![]),
+            Device::GenericDevice(x) => (x.device_type.name(), 0, 0, vec![]),
         };
         Ok((kind, frequency, signal_strength, ssid))
     }
@@ -299,3 +725,25 @@ impl Debug for Device<'_> {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_type_from_u32() {
+        let cases = [
+            (0, NMDeviceType::Unknown),
+            (1, NMDeviceType::Ethernet),
+            (2, NMDeviceType::Wifi),
+            (29, NMDeviceType::Wireguard),
+            (34, NMDeviceType::Ipvlan),
+            // Added to NetworkManager after this list
+            (35, NMDeviceType::Unknown),
+            (u32::MAX, NMDeviceType::Unknown),
+        ];
+        for (device_type, expected) in cases {
+            assert_eq!(NMDeviceType::from(device_type), expected, "{}", device_type);
+        }
+    }
+}
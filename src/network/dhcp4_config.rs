@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use zbus::{Connection, Proxy};
+use zvariant::{OwnedObjectPath, OwnedValue};
+
+use crate::error::Error;
+
+pub struct Dhcp4Config<'a> {
+    path: String,
+    proxy: Proxy<'a>,
+}
+
+impl Dhcp4Config<'_> {
+    pub async fn new<'a>(
+        path: OwnedObjectPath,
+        connection: &'a Connection,
+    ) -> Result<Dhcp4Config<'a>, Error> {
+        let p = Proxy::new(
+            connection,
+            "org.freedesktop.NetworkManager",
+            path.clone(),
+            "org.freedesktop.NetworkManager.DHCP4Config",
+        )
+        .await?;
+        Ok(Dhcp4Config {
+            path: path.to_string(),
+            proxy: p,
+        })
+    }
+
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    /// Options and configuration returned by the DHCP server, every value is a string
+    pub async fn get_options(&self) -> Result<HashMap<String, OwnedValue>, Error> {
+        Ok(self
+            .proxy
+            .get_property::<HashMap<String, OwnedValue>>("Options")
+            .await?)
+    }
+
+    pub async fn get_option(&self, name: &str) -> Result<Option<String>, Error> {
+        match self.get_options().await?.get(name) {
+            Some(value) => Ok(Some(<&str>::try_from(value)?.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Lease time in seconds
+    pub async fn get_lease_time(&self) -> Result<Option<u32>, Error> {
+        match self.get_option("dhcp_lease_time").await? {
+            Some(lease_time) => Ok(Some(lease_time.parse()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Address of the DHCP server which gave the lease
+    pub async fn get_server(&self) -> Result<Option<String>, Error> {
+        self.get_option("dhcp_server_identifier").await
+    }
+}
@@ -0,0 +1,318 @@
+use std::time::Duration;
+
+use clap::{Args, ValueEnum};
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use zbus::{zvariant::OwnedObjectPath, Connection, Proxy};
+
+use super::{
+    active_connection::ActiveConnection, follow_activation, networkmanager::NetworkManager,
+    receive_properties_changed, settings, watch_service,
+};
+use crate::error::{report, Error};
+
+/// NM_WIFI_DEVICE_CAP_AP, the device supports Access Point mode
+const WIFI_DEVICE_CAP_AP: u32 = 0x40;
+/// NM_WIFI_DEVICE_CAP_FREQ_5GHZ, the device supports 5GHz frequencies
+const WIFI_DEVICE_CAP_FREQ_5GHZ: u32 = 0x400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Band {
+    /// 2.4GHz, supported by every client
+    #[value(name = "2.4")]
+    Bg,
+    /// 5GHz, faster but shorter range
+    #[value(name = "5")]
+    A,
+}
+
+impl Band {
+    /// Value of the "band" setting of NetworkManager
+    fn setting(&self) -> &'static str {
+        match self {
+            Band::Bg => "bg",
+            Band::A => "a",
+        }
+    }
+
+    fn from_setting(setting: &str) -> Option<Band> {
+        match setting {
+            "bg" => Some(Band::Bg),
+            "a" => Some(Band::A),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Band::Bg => "2.4",
+            Band::A => "5",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct HotspotConfig {
+    /// Name of the network shown to the clients
+    #[arg(long)]
+    pub ssid: String,
+    /// WPA2 password, between 8 and 63 characters. The hotspot is open without it
+    #[arg(long)]
+    pub password: Option<String>,
+    #[arg(long, value_enum, default_value_t = Band::Bg)]
+    pub band: Band,
+    /// Wireless interface to use, the first wireless device by default
+    #[arg(long)]
+    pub interface: Option<String>,
+    /// Name of the connection profile saved for the hotspot
+    #[arg(long, default_value = "Hotspot")]
+    pub name: String,
+}
+
+/// Time between two counts of the connected clients
+const CLIENTS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Create the hotspot profile and activate it, replacing a previous hotspot with the same name
+pub(crate) async fn start(config: HotspotConfig) -> Result<(), Error> {
+    if let Some(password) = &config.password {
+        if !(8..=63).contains(&password.len()) {
+            return Err("the WPA2 password must be between 8 and 63 characters".into());
+        }
+    }
+
+    let connection = Connection::system().await?;
+
+    let nm = NetworkManager::new(&connection).await?;
+    let settings = settings::Settings::new(&connection).await?;
+
+    let mut device = None;
+    for wireless_device in nm.get_wireless_devices().await? {
+        match &config.interface {
+            Some(interface) if &wireless_device.get_interface().await? != interface => continue,
+            _ => {
+                device = Some(wireless_device);
+                break;
+            }
+        }
+    }
+    let device = match (device, &config.interface) {
+        (Some(device), _) => device,
+        (None, Some(interface)) => {
+            return Err(format!("no wireless device named \"{}\"", interface).into())
+        }
+        (None, None) => return Err("no wireless device found".into()),
+    };
+
+    let capabilities = device.get_wireless_capabilities().await?;
+    if capabilities & WIFI_DEVICE_CAP_AP == 0 {
+        return Err("the wireless device does not support access point mode".into());
+    }
+    if config.band == Band::A && capabilities & WIFI_DEVICE_CAP_FREQ_5GHZ == 0 {
+        return Err("the wireless device does not support 5GHz".into());
+    }
+
+    if let Some(saved) = settings.get_connection_by_id(&config.name).await? {
+        if saved
+            .get_string_setting("802-11-wireless", "mode")
+            .await?
+            .as_deref()
+            != Some("ap")
+        {
+            return Err(format!("\"{}\" is already the name of a connection", config.name).into());
+        }
+        saved.delete().await?;
+    }
+
+    let active_connection = nm
+        .add_and_activate_connection(
+            settings::hotspot_settings(
+                &config.name,
+                &config.ssid,
+                config.band.setting(),
+                config.password.as_deref(),
+            ),
+            device.path(),
+            "/",
+        )
+        .await?;
+    follow_activation(&active_connection, "name", &config.name).await
+}
+
+/// Deactivate every active hotspot
+pub(crate) async fn stop() -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let nm = NetworkManager::new(&connection).await?;
+    let hotspots = get_hotspots(&nm).await?;
+    if hotspots.is_empty() {
+        return Err("no active hotspot".into());
+    }
+    for hotspot in hotspots {
+        let name = hotspot.get_id().await?;
+        nm.deactivate_connection(&hotspot).await?;
+        println!("{}", json!({ "name": name, "state": "Deactivated" }));
+    }
+    Ok(())
+}
+
+/// Output a json whenever the hotspot state or its number of connected clients change
+///
+/// ``` json
+/// {
+///   "active": true,
+///   "name": "Hotspot",
+///   "ssid": "laptop",
+///   "interface": "wlan0",
+///   "band": "2.4",
+///   "state": "Activated",
+///   "clients": 2
+/// }
+/// ```
+pub(crate) async fn listener() -> Result<(), Error> {
+    let connection = Connection::system().await?;
+    watch_service(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        json!({
+            "active": false,
+            "name": null,
+            "ssid": null,
+            "interface": null,
+            "band": null,
+            "state": "Unavailable",
+            "clients": 0,
+        }),
+        || listen(&connection),
+    )
+    .await
+}
+
+async fn listen(connection: &Connection) -> Result<(), Error> {
+    let nm = NetworkManager::new(connection).await?;
+
+    // NetworkManager does not expose the stations, they are polled from wpa_supplicant
+    let clients_interval = tokio::time::interval(CLIENTS_INTERVAL);
+    let mut events = futures_util::stream::select_all(vec![
+        nm.proxy
+            .receive_active_connections_changed()
+            .await
+            .map(|_| ())
+            .boxed(),
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/ActiveConnection",
+            "org.freedesktop.NetworkManager.Connection.Active",
+            &["State"],
+        )
+        .await?,
+        futures_util::stream::unfold(clients_interval, |mut interval| async move {
+            interval.tick().await;
+            Some(((), interval))
+        })
+        .boxed(),
+    ]);
+
+    let mut last = None;
+    while events.next().await.is_some() {
+        report(
+            async {
+                let hotspot = hotspot_data(connection, &nm).await?;
+                // The clients are polled, only print actual changes
+                if last.as_ref() != Some(&hotspot) {
+                    println!("{}", hotspot);
+                    last = Some(hotspot);
+                }
+                Ok(())
+            }
+            .await,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Active connections of access point profiles
+async fn get_hotspots<'a>(nm: &'a NetworkManager<'_>) -> Result<Vec<ActiveConnection<'a>>, Error> {
+    let mut hotspots = vec![];
+    for active_connection in nm.get_active_connections().await? {
+        // Connections can vanish between the listing and the settings read
+        let Ok(settings_connection) = active_connection.get_settings_connection().await else {
+            continue;
+        };
+        if let Ok(Some(mode)) = settings_connection
+            .get_string_setting("802-11-wireless", "mode")
+            .await
+        {
+            if mode == "ap" {
+                hotspots.push(active_connection);
+            }
+        }
+    }
+    Ok(hotspots)
+}
+
+async fn hotspot_data(connection: &Connection, nm: &NetworkManager<'_>) -> Result<Value, Error> {
+    let hotspots = get_hotspots(nm).await?;
+    let Some(hotspot) = hotspots.first() else {
+        return Ok(json!({
+            "active": false,
+            "name": null,
+            "ssid": null,
+            "interface": null,
+            "band": null,
+            "state": null,
+            "clients": 0,
+        }));
+    };
+
+    let settings_connection = hotspot.get_settings_connection().await?;
+    let ssid = settings_connection.get_ssid().await?;
+    let band = settings_connection
+        .get_string_setting("802-11-wireless", "band")
+        .await?
+        .as_deref()
+        .and_then(Band::from_setting)
+        .map(|band| band.name());
+    let interface = match hotspot.get_devices().await?.first() {
+        Some(device) => Some(device.get_interface().await?),
+        None => None,
+    };
+    // Null when the access point is not run by wpa_supplicant (e.g. the iwd backend)
+    let clients = match &interface {
+        Some(interface) => count_clients(connection, interface).await.ok(),
+        None => None,
+    };
+
+    Ok(json!({
+        "active": true,
+        "name": hotspot.get_id().await?,
+        "ssid": ssid,
+        "interface": interface,
+        "band": band,
+        "state": hotspot.get_state().await?.to_string(),
+        "clients": clients,
+    }))
+}
+
+/// Number of stations associated to the access point run by wpa_supplicant on `interface`
+async fn count_clients(connection: &Connection, interface: &str) -> Result<usize, Error> {
+    let wpa_supplicant = Proxy::new(
+        connection,
+        "fi.w1.wpa_supplicant1",
+        "/fi/w1/wpa_supplicant1",
+        "fi.w1.wpa_supplicant1",
+    )
+    .await?;
+    let interface_path: OwnedObjectPath = wpa_supplicant.call("GetInterface", &interface).await?;
+    let wpa_interface = Proxy::new(
+        connection,
+        "fi.w1.wpa_supplicant1",
+        interface_path,
+        "fi.w1.wpa_supplicant1.Interface",
+    )
+    .await?;
+    Ok(wpa_interface
+        .get_property::<Vec<OwnedObjectPath>>("Stations")
+        .await?
+        .len())
+}
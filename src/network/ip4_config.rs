@@ -1,9 +1,12 @@
-use std::{collections::HashMap, error::Error};
+use std::collections::HashMap;
 
 use zbus::{Connection, Proxy};
 use zvariant::{OwnedObjectPath, OwnedValue};
 
+use crate::error::Error;
+
 #[derive(Debug, OwnedValue)]
+#[allow(clippy::enum_variant_names)]
 pub enum NMActiveConnectionState {
     UnknownConnection = 0,
     ActivatingConnection = 1,
@@ -19,11 +22,24 @@ pub struct Address {
 }
 
 impl Address {
-    fn from_raw(
+    /// Whether the address is an IPv6 link-local address (fe80::/10)
+    pub fn is_link_local(&self) -> bool {
+        self.address
+            .parse::<std::net::Ipv6Addr>()
+            .is_ok_and(|address| address.segments()[0] & 0xffc0 == 0xfe80)
+    }
+
+    pub(super) fn from_raw(
         address_raw: HashMap<String, zvariant::OwnedValue>,
-    ) -> Result<Address, Box<dyn Error>> {
-        let prefix: u32 = address_raw.get("prefix").unwrap().try_into()?;
-        let address: &str = address_raw.get("address").unwrap().try_into()?;
+    ) -> Result<Address, Error> {
+        let prefix: u32 = address_raw
+            .get("prefix")
+            .ok_or_else(|| Error::Parse("address without prefix".to_string()))?
+            .try_into()?;
+        let address: &str = address_raw
+            .get("address")
+            .ok_or_else(|| Error::Parse("address without address".to_string()))?
+            .try_into()?;
         Ok(Address {
             address: address.to_string(),
             mask_cird: prefix.to_string(),
@@ -31,6 +47,44 @@ impl Address {
     }
 }
 
+#[derive(Debug)]
+pub struct Route {
+    pub dest: String,
+    pub prefix: u32,
+    /// Empty for routes without gateway
+    pub next_hop: String,
+    pub metric: Option<u32>,
+}
+
+impl Route {
+    pub(super) fn from_raw(
+        route_raw: HashMap<String, zvariant::OwnedValue>,
+    ) -> Result<Route, Error> {
+        let dest: &str = route_raw
+            .get("dest")
+            .ok_or_else(|| Error::Parse("route without dest".to_string()))?
+            .try_into()?;
+        let prefix: u32 = route_raw
+            .get("prefix")
+            .ok_or_else(|| Error::Parse("route without prefix".to_string()))?
+            .try_into()?;
+        let next_hop = match route_raw.get("next-hop") {
+            Some(next_hop) => <&str>::try_from(next_hop)?.to_string(),
+            None => "".to_string(),
+        };
+        let metric = match route_raw.get("metric") {
+            Some(metric) => Some(u32::try_from(metric)?),
+            None => None,
+        };
+        Ok(Route {
+            dest: dest.to_string(),
+            prefix,
+            next_hop,
+            metric,
+        })
+    }
+}
+
 pub struct Ip4Config<'a> {
     path: String,
     proxy: Proxy<'a>,
@@ -40,7 +94,7 @@ impl Ip4Config<'_> {
     pub async fn new<'a>(
         path: OwnedObjectPath,
         connection: &'a Connection,
-    ) -> Result<Ip4Config<'a>, Box<dyn Error>> {
+    ) -> Result<Ip4Config<'a>, Error> {
         let p = Proxy::new(
             connection,
             "org.freedesktop.NetworkManager",
@@ -58,15 +112,55 @@ impl Ip4Config<'_> {
         &self.path
     }
 
-    pub async fn get_gateway(&self) -> Result<String, Box<dyn Error>> {
+    pub async fn get_gateway(&self) -> Result<String, Error> {
         Ok(self.proxy.get_property::<String>("Gateway").await?)
     }
 
-    pub async fn get_domains(&self) -> Result<Vec<String>, Box<dyn Error>> {
+    pub async fn get_domains(&self) -> Result<Vec<String>, Error> {
         Ok(self.proxy.get_property::<Vec<String>>("Domains").await?)
     }
 
-    pub async fn get_addresses(&self) -> Result<Vec<Address>, Box<dyn Error>> {
+    /// DNS search domains
+    pub async fn get_searches(&self) -> Result<Vec<String>, Error> {
+        Ok(self.proxy.get_property::<Vec<String>>("Searches").await?)
+    }
+
+    pub async fn get_nameservers(&self) -> Result<Vec<String>, Error> {
+        let nameservers_raw = self
+            .proxy
+            .get_property::<Vec<HashMap<String, zvariant::OwnedValue>>>("NameserverData")
+            .await?;
+        let mut nameservers = vec![];
+        for nameserver_raw in nameservers_raw {
+            let address: &str = nameserver_raw
+                .get("address")
+                .ok_or("nameserver without address")?
+                .try_into()?;
+            nameservers.push(address.to_string());
+        }
+        Ok(nameservers)
+    }
+
+    pub async fn get_wins_servers(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .proxy
+            .get_property::<Vec<String>>("WinsServerData")
+            .await?)
+    }
+
+    pub async fn get_routes(&self) -> Result<Vec<Route>, Error> {
+        let routes_raw = self
+            .proxy
+            .get_property::<Vec<HashMap<String, zvariant::OwnedValue>>>("RouteData")
+            .await?;
+        let mut routes: Vec<Route> = vec![];
+        for route_raw in routes_raw {
+            routes.push(Route::from_raw(route_raw)?);
+        }
+        Ok(routes)
+    }
+
+    pub async fn get_addresses(&self) -> Result<Vec<Address>, Error> {
         let addresses_raw = self
             .proxy
             .get_property::<Vec<HashMap<String, zvariant::OwnedValue>>>("AddressData")
@@ -0,0 +1,86 @@
+use std::{collections::HashMap, net::Ipv6Addr};
+
+use zbus::{Connection, Proxy};
+use zvariant::OwnedObjectPath;
+
+use super::ip4_config::{Address, Route};
+use crate::error::Error;
+
+pub struct Ip6Config<'a> {
+    path: String,
+    proxy: Proxy<'a>,
+}
+
+impl Ip6Config<'_> {
+    pub async fn new<'a>(
+        path: OwnedObjectPath,
+        connection: &'a Connection,
+    ) -> Result<Ip6Config<'a>, Error> {
+        let p = Proxy::new(
+            connection,
+            "org.freedesktop.NetworkManager",
+            path.clone(),
+            "org.freedesktop.NetworkManager.IP6Config",
+        )
+        .await?;
+        Ok(Ip6Config {
+            path: path.to_string(),
+            proxy: p,
+        })
+    }
+
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    pub async fn get_gateway(&self) -> Result<String, Error> {
+        Ok(self.proxy.get_property::<String>("Gateway").await?)
+    }
+
+    pub async fn get_domains(&self) -> Result<Vec<String>, Error> {
+        Ok(self.proxy.get_property::<Vec<String>>("Domains").await?)
+    }
+
+    pub async fn get_searches(&self) -> Result<Vec<String>, Error> {
+        Ok(self.proxy.get_property::<Vec<String>>("Searches").await?)
+    }
+
+    pub async fn get_nameservers(&self) -> Result<Vec<String>, Error> {
+        let nameservers_raw = self
+            .proxy
+            .get_property::<Vec<Vec<u8>>>("Nameservers")
+            .await?;
+        let mut nameservers = vec![];
+        for nameserver_raw in nameservers_raw {
+            let octets: [u8; 16] = nameserver_raw
+                .try_into()
+                .map_err(|_| "IPv6 nameserver is not 16 bytes long")?;
+            nameservers.push(Ipv6Addr::from(octets).to_string());
+        }
+        Ok(nameservers)
+    }
+
+    pub async fn get_addresses(&self) -> Result<Vec<Address>, Error> {
+        let addresses_raw = self
+            .proxy
+            .get_property::<Vec<HashMap<String, zvariant::OwnedValue>>>("AddressData")
+            .await?;
+        let mut addresses: Vec<Address> = vec![];
+        for address_raw in addresses_raw {
+            addresses.push(Address::from_raw(address_raw)?);
+        }
+        Ok(addresses)
+    }
+
+    pub async fn get_routes(&self) -> Result<Vec<Route>, Error> {
+        let routes_raw = self
+            .proxy
+            .get_property::<Vec<HashMap<String, zvariant::OwnedValue>>>("RouteData")
+            .await?;
+        let mut routes: Vec<Route> = vec![];
+        for route_raw in routes_raw {
+            routes.push(Route::from_raw(route_raw)?);
+        }
+        Ok(routes)
+    }
+}
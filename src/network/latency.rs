@@ -0,0 +1,190 @@
+use std::{collections::VecDeque, time::Duration};
+
+use clap::Args;
+use serde_json::json;
+
+use super::ping::IcmpPinger;
+use crate::error::Error;
+
+#[derive(Debug, Clone, Args)]
+pub struct LatencyConfig {
+    /// Host to ping, a name or an IP address
+    #[arg(long, default_value = "1.1.1.1")]
+    pub target: String,
+    /// Time between two pings in milliseconds
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+    /// Time to wait for a reply in milliseconds
+    #[arg(long, default_value_t = 1000)]
+    pub timeout: u64,
+    /// Number of pings the statistics are computed on
+    #[arg(long, default_value_t = 30)]
+    pub window: usize,
+}
+
+/// Round trips of the last pings, `None` for the lost ones
+struct LatencyWindow {
+    samples: VecDeque<Option<Duration>>,
+    size: usize,
+}
+
+impl LatencyWindow {
+    fn new(size: usize) -> LatencyWindow {
+        LatencyWindow {
+            samples: VecDeque::with_capacity(size),
+            size: size.max(1),
+        }
+    }
+
+    fn push(&mut self, sample: Option<Duration>) {
+        if self.samples.len() == self.size {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let as_ms = |rtt: &Duration| rtt.as_micros() as f64 / 1000.0;
+        let received: Vec<f64> = self.samples.iter().flatten().map(as_ms).collect();
+        let lost = self.samples.len() - received.len();
+
+        let min = received.iter().copied().reduce(f64::min);
+        let max = received.iter().copied().reduce(f64::max);
+        let avg =
+            (!received.is_empty()).then(|| received.iter().sum::<f64>() / received.len() as f64);
+        // Mean deviation between consecutive round trips
+        let jitter = (received.len() > 1).then(|| {
+            let deviations = received.windows(2).map(|pair| (pair[1] - pair[0]).abs());
+            deviations.sum::<f64>() / (received.len() - 1) as f64
+        });
+        let loss = match self.samples.len() {
+            0 => 0.0,
+            total => lost as f64 * 100.0 / total as f64,
+        };
+
+        // Microsecond precision is enough for a bar
+        let round = |value: f64| (value * 1000.0).round() / 1000.0;
+        json!({
+            "last": self.samples.back().copied().flatten().as_ref().map(as_ms),
+            "min": min,
+            "avg": avg.map(round),
+            "max": max,
+            "jitter": jitter.map(round),
+            "loss": round(loss),
+            "history": self.samples.iter().map(|rtt| rtt.as_ref().map(as_ms)).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Output a json with the statistics of the last pings after each ping.
+/// Round trips are in milliseconds, null when lost or without any reply
+///
+/// ``` json
+/// {
+///   "target": "1.1.1.1",
+///   "last": 12.3,
+///   "min": 10.1,
+///   "avg": 14.8,
+///   "max": 31.0,
+///   "jitter": 3.2,
+///   "loss": 3.33,
+///   "history": [10.1, null, 12.3]
+/// }
+/// ```
+pub(crate) async fn listener(config: LatencyConfig) -> Result<(), Error> {
+    let mut pinger = IcmpPinger::new(&config.target).await?;
+    pinger.timeout(Duration::from_millis(config.timeout));
+
+    let mut window = LatencyWindow::new(config.window);
+    let mut interval = tokio::time::interval(Duration::from_millis(config.interval));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let rtt = pinger.ping().await.ok().map(|(_, rtt)| rtt);
+        window.push(rtt);
+
+        let mut stats = window.to_json();
+        stats["target"] = json!(config.target);
+        println!("{}", stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_window() {
+        let ms = |ms: u64| Some(Duration::from_millis(ms));
+        let cases = [
+            (
+                vec![],
+                json!({
+                    "last": null,
+                    "min": null,
+                    "avg": null,
+                    "max": null,
+                    "jitter": null,
+                    "loss": 0.0,
+                    "history": [],
+                }),
+            ),
+            (
+                vec![ms(10)],
+                json!({
+                    "last": 10.0,
+                    "min": 10.0,
+                    "avg": 10.0,
+                    "max": 10.0,
+                    "jitter": null,
+                    "loss": 0.0,
+                    "history": [10.0],
+                }),
+            ),
+            (
+                vec![ms(10), ms(30), ms(20)],
+                json!({
+                    "last": 20.0,
+                    "min": 10.0,
+                    "avg": 20.0,
+                    "max": 30.0,
+                    "jitter": 15.0,
+                    "loss": 0.0,
+                    "history": [10.0, 30.0, 20.0],
+                }),
+            ),
+            (
+                vec![ms(10), None, ms(20), None],
+                json!({
+                    "last": null,
+                    "min": 10.0,
+                    "avg": 15.0,
+                    "max": 20.0,
+                    "jitter": 10.0,
+                    "loss": 50.0,
+                    "history": [10.0, null, 20.0, null],
+                }),
+            ),
+            // Only the last 4 samples are kept
+            (
+                vec![None, None, ms(40), ms(10), ms(30), ms(20)],
+                json!({
+                    "last": 20.0,
+                    "min": 10.0,
+                    "avg": 25.0,
+                    "max": 40.0,
+                    "jitter": 20.0,
+                    "loss": 0.0,
+                    "history": [40.0, 10.0, 30.0, 20.0],
+                }),
+            ),
+        ];
+        for (samples, expected) in cases {
+            let mut window = LatencyWindow::new(4);
+            for sample in &samples {
+                window.push(*sample);
+            }
+            assert_eq!(window.to_json(), expected, "{:?}", samples);
+        }
+    }
+}
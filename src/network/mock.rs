@@ -0,0 +1,616 @@
+//! In-process fake of the `org.freedesktop.NetworkManager` service, served on a private bus.
+//!
+//! Describe the devices, access points and connections with the `Mock*` structs,
+//! then [`MockNetworkManager::serve`] them on a [`MockBus`] and connect the wrappers to it
+//! with [`MockBus::connect`].
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+};
+
+use zbus::{dbus_interface, Connection, ConnectionBuilder};
+use zvariant::{OwnedObjectPath, OwnedValue};
+
+/// A `dbus-daemon --session` owned by a test, killed on drop
+pub struct MockBus {
+    daemon: Child,
+    address: String,
+}
+
+impl MockBus {
+    /// Start a private bus, `None` when dbus-daemon is not installed
+    pub fn start() -> Option<MockBus> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.as_mut()?)
+            .read_line(&mut address)
+            .ok()?;
+        Some(MockBus {
+            daemon,
+            address: address.trim().to_string(),
+        })
+    }
+
+    /// A new client connection to the bus
+    pub async fn connect(&self) -> zbus::Result<Connection> {
+        ConnectionBuilder::address(self.address.as_str())?
+            .build()
+            .await
+    }
+}
+
+impl Drop for MockBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockAccessPoint {
+    pub ssid: Vec<u8>,
+    pub hw_address: String,
+    pub strength: u8,
+    pub frequency: u32,
+    pub max_bitrate: u32,
+    pub flags: u32,
+    pub wpa_flags: u32,
+    pub rsn_flags: u32,
+    /// NM80211Mode, infrastructure by default
+    pub mode: u32,
+    pub last_seen: i32,
+}
+
+impl MockAccessPoint {
+    /// An open infrastructure access point on channel 1
+    pub fn new(ssid: &[u8], strength: u8) -> MockAccessPoint {
+        MockAccessPoint {
+            ssid: ssid.to_vec(),
+            hw_address: "AA:BB:CC:DD:EE:FF".to_string(),
+            strength,
+            frequency: 2412,
+            max_bitrate: 54000,
+            mode: 2,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockDevice {
+    pub interface: String,
+    /// NMDeviceType
+    pub device_type: u32,
+    /// NMDeviceState
+    pub state: u32,
+    pub hw_address: String,
+    /// Only served for Wi-Fi devices
+    pub access_points: Vec<MockAccessPoint>,
+    /// Index in `access_points`
+    pub active_access_point: Option<usize>,
+}
+
+impl MockDevice {
+    /// An activated Ethernet device
+    pub fn ethernet(interface: &str) -> MockDevice {
+        MockDevice {
+            interface: interface.to_string(),
+            device_type: 1,
+            state: 100,
+            hw_address: "00:11:22:33:44:55".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// An activated Wi-Fi device, associated with the first of `access_points`
+    pub fn wifi(interface: &str, access_points: Vec<MockAccessPoint>) -> MockDevice {
+        MockDevice {
+            interface: interface.to_string(),
+            device_type: 2,
+            state: 100,
+            hw_address: "66:77:88:99:AA:BB".to_string(),
+            active_access_point: (!access_points.is_empty()).then_some(0),
+            access_points,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockIpConfig {
+    pub gateway: String,
+    /// Address and prefix
+    pub addresses: Vec<(String, u32)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockActiveConnection {
+    pub id: String,
+    pub uuid: String,
+    pub kind: String,
+    /// NMActiveConnectionState
+    pub state: u32,
+    pub vpn: bool,
+    pub default: bool,
+    /// Indexes in [`MockNetworkManager::devices`]
+    pub devices: Vec<usize>,
+    pub ip4: Option<MockIpConfig>,
+    pub ip6: Option<MockIpConfig>,
+}
+
+impl MockActiveConnection {
+    /// An activated connection on `devices`
+    pub fn new(id: &str, kind: &str, devices: Vec<usize>) -> MockActiveConnection {
+        MockActiveConnection {
+            id: id.to_string(),
+            uuid: format!("mock-{}", id),
+            kind: kind.to_string(),
+            state: 2,
+            devices,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockNetworkManager {
+    /// NMState
+    pub state: u32,
+    /// NMConnectivityState
+    pub connectivity: u32,
+    pub connectivity_check_uri: String,
+    pub networking_enabled: bool,
+    pub wireless_enabled: bool,
+    pub devices: Vec<MockDevice>,
+    pub active_connections: Vec<MockActiveConnection>,
+    /// Index in `active_connections`
+    pub primary_connection: Option<usize>,
+}
+
+impl MockNetworkManager {
+    /// Globally connected through `active_connections[0]` when there is one
+    pub fn new(
+        devices: Vec<MockDevice>,
+        active_connections: Vec<MockActiveConnection>,
+    ) -> MockNetworkManager {
+        let connected = !active_connections.is_empty();
+        MockNetworkManager {
+            state: if connected { 70 } else { 20 },
+            connectivity: if connected { 4 } else { 1 },
+            networking_enabled: true,
+            wireless_enabled: true,
+            primary_connection: connected.then_some(0),
+            devices,
+            active_connections,
+            ..Default::default()
+        }
+    }
+
+    /// Export every object on a new connection owning "org.freedesktop.NetworkManager".
+    /// The service lives as long as the returned connection.
+    pub async fn serve(&self, bus: &MockBus) -> zbus::Result<Connection> {
+        let connection = bus.connect().await?;
+        let server = connection.object_server();
+
+        let mut access_point_count = 0;
+        for (index, device) in self.devices.iter().enumerate() {
+            let active_connection = self
+                .active_connections
+                .iter()
+                .position(|active_connection| active_connection.devices.contains(&index))
+                .map(active_connection_path)
+                .unwrap_or_else(root_path);
+            server
+                .at(
+                    device_path(index),
+                    DeviceObject {
+                        device: device.clone(),
+                        active_connection,
+                    },
+                )
+                .await?;
+
+            if device.device_type != 2 {
+                continue;
+            }
+            let mut access_points = vec![];
+            for access_point in &device.access_points {
+                let path = access_point_path(access_point_count);
+                access_point_count += 1;
+                server.at(path.clone(), access_point.clone()).await?;
+                access_points.push(path);
+            }
+            let active_access_point = device
+                .active_access_point
+                .map(|active| access_points[active].clone())
+                .unwrap_or_else(root_path);
+            server
+                .at(
+                    device_path(index),
+                    WirelessObject {
+                        access_points,
+                        active_access_point,
+                    },
+                )
+                .await?;
+        }
+
+        for (index, active_connection) in self.active_connections.iter().enumerate() {
+            if let Some(ip4) = &active_connection.ip4 {
+                server
+                    .at(ip4_config_path(index), Ip4ConfigObject(ip4.clone()))
+                    .await?;
+            }
+            if let Some(ip6) = &active_connection.ip6 {
+                server
+                    .at(ip6_config_path(index), Ip6ConfigObject(ip6.clone()))
+                    .await?;
+            }
+            server
+                .at(
+                    active_connection_path(index),
+                    ActiveConnectionObject {
+                        active_connection: active_connection.clone(),
+                        index,
+                    },
+                )
+                .await?;
+        }
+
+        server
+            .at("/org/freedesktop/NetworkManager", self.clone())
+            .await?;
+        drop(server);
+        connection
+            .request_name("org.freedesktop.NetworkManager")
+            .await?;
+        Ok(connection)
+    }
+}
+
+pub fn device_path(index: usize) -> OwnedObjectPath {
+    object_path(format!("/org/freedesktop/NetworkManager/Devices/{}", index))
+}
+
+pub fn access_point_path(index: usize) -> OwnedObjectPath {
+    object_path(format!(
+        "/org/freedesktop/NetworkManager/AccessPoint/{}",
+        index
+    ))
+}
+
+pub fn active_connection_path(index: usize) -> OwnedObjectPath {
+    object_path(format!(
+        "/org/freedesktop/NetworkManager/ActiveConnection/{}",
+        index
+    ))
+}
+
+fn ip4_config_path(index: usize) -> OwnedObjectPath {
+    object_path(format!(
+        "/org/freedesktop/NetworkManager/IP4Config/{}",
+        index
+    ))
+}
+
+fn ip6_config_path(index: usize) -> OwnedObjectPath {
+    object_path(format!(
+        "/org/freedesktop/NetworkManager/IP6Config/{}",
+        index
+    ))
+}
+
+fn root_path() -> OwnedObjectPath {
+    object_path("/".to_string())
+}
+
+fn object_path(path: String) -> OwnedObjectPath {
+    OwnedObjectPath::try_from(path).expect("valid object path")
+}
+
+/// Change the strength of a served access point, emitting PropertiesChanged
+pub async fn set_strength(
+    service: &Connection,
+    path: OwnedObjectPath,
+    strength: u8,
+) -> zbus::Result<()> {
+    let access_point = service
+        .object_server()
+        .interface::<_, MockAccessPoint>(path)
+        .await?;
+    access_point.get_mut().await.strength = strength;
+    let changed = access_point
+        .get()
+        .await
+        .strength_changed(access_point.signal_context())
+        .await;
+    changed
+}
+
+#[dbus_interface(name = "org.freedesktop.NetworkManager")]
+impl MockNetworkManager {
+    fn get_devices(&self) -> Vec<OwnedObjectPath> {
+        (0..self.devices.len()).map(device_path).collect()
+    }
+
+    fn check_connectivity(&self) -> u32 {
+        self.connectivity
+    }
+
+    #[dbus_interface(property)]
+    fn state(&self) -> u32 {
+        self.state
+    }
+
+    #[dbus_interface(property)]
+    fn connectivity(&self) -> u32 {
+        self.connectivity
+    }
+
+    #[dbus_interface(property)]
+    fn connectivity_check_uri(&self) -> String {
+        self.connectivity_check_uri.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn networking_enabled(&self) -> bool {
+        self.networking_enabled
+    }
+
+    #[dbus_interface(property)]
+    fn wireless_enabled(&self) -> bool {
+        self.wireless_enabled
+    }
+
+    #[dbus_interface(property)]
+    fn active_connections(&self) -> Vec<OwnedObjectPath> {
+        (0..self.active_connections.len())
+            .map(active_connection_path)
+            .collect()
+    }
+
+    #[dbus_interface(property)]
+    fn primary_connection(&self) -> OwnedObjectPath {
+        self.primary_connection
+            .map(active_connection_path)
+            .unwrap_or_else(root_path)
+    }
+
+    #[dbus_interface(property)]
+    fn primary_connection_type(&self) -> String {
+        self.primary_connection
+            .map(|primary| self.active_connections[primary].kind.clone())
+            .unwrap_or_default()
+    }
+}
+
+struct DeviceObject {
+    device: MockDevice,
+    active_connection: OwnedObjectPath,
+}
+
+#[dbus_interface(name = "org.freedesktop.NetworkManager.Device")]
+impl DeviceObject {
+    #[dbus_interface(property)]
+    fn interface(&self) -> String {
+        self.device.interface.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn device_type(&self) -> u32 {
+        self.device.device_type
+    }
+
+    #[dbus_interface(property)]
+    fn state(&self) -> u32 {
+        self.device.state
+    }
+
+    #[dbus_interface(property)]
+    fn hw_address(&self) -> String {
+        self.device.hw_address.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn udi(&self) -> String {
+        format!("/sys/devices/virtual/net/{}", self.device.interface)
+    }
+
+    #[dbus_interface(property)]
+    fn active_connection(&self) -> OwnedObjectPath {
+        self.active_connection.clone()
+    }
+}
+
+struct WirelessObject {
+    access_points: Vec<OwnedObjectPath>,
+    active_access_point: OwnedObjectPath,
+}
+
+#[dbus_interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+impl WirelessObject {
+    #[dbus_interface(property)]
+    fn access_points(&self) -> Vec<OwnedObjectPath> {
+        self.access_points.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn active_access_point(&self) -> OwnedObjectPath {
+        self.active_access_point.clone()
+    }
+}
+
+#[dbus_interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+impl MockAccessPoint {
+    #[dbus_interface(property)]
+    fn ssid(&self) -> Vec<u8> {
+        self.ssid.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn hw_address(&self) -> String {
+        self.hw_address.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn strength(&self) -> u8 {
+        self.strength
+    }
+
+    #[dbus_interface(property)]
+    fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    #[dbus_interface(property)]
+    fn max_bitrate(&self) -> u32 {
+        self.max_bitrate
+    }
+
+    #[dbus_interface(property)]
+    fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    #[dbus_interface(property)]
+    fn wpa_flags(&self) -> u32 {
+        self.wpa_flags
+    }
+
+    #[dbus_interface(property)]
+    fn rsn_flags(&self) -> u32 {
+        self.rsn_flags
+    }
+
+    #[dbus_interface(property)]
+    fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    #[dbus_interface(property)]
+    fn last_seen(&self) -> i32 {
+        self.last_seen
+    }
+}
+
+struct ActiveConnectionObject {
+    active_connection: MockActiveConnection,
+    index: usize,
+}
+
+#[dbus_interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+impl ActiveConnectionObject {
+    #[dbus_interface(property)]
+    fn id(&self) -> String {
+        self.active_connection.id.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn uuid(&self) -> String {
+        self.active_connection.uuid.clone()
+    }
+
+    #[dbus_interface(property, name = "Type")]
+    fn kind(&self) -> String {
+        self.active_connection.kind.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn state(&self) -> u32 {
+        self.active_connection.state
+    }
+
+    #[dbus_interface(property)]
+    fn vpn(&self) -> bool {
+        self.active_connection.vpn
+    }
+
+    #[dbus_interface(property)]
+    fn default(&self) -> bool {
+        self.active_connection.default
+    }
+
+    #[dbus_interface(property)]
+    fn default6(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn devices(&self) -> Vec<OwnedObjectPath> {
+        self.active_connection
+            .devices
+            .iter()
+            .copied()
+            .map(device_path)
+            .collect()
+    }
+
+    #[dbus_interface(property)]
+    fn ip4_config(&self) -> OwnedObjectPath {
+        match self.active_connection.ip4 {
+            Some(_) => ip4_config_path(self.index),
+            None => root_path(),
+        }
+    }
+
+    #[dbus_interface(property)]
+    fn ip6_config(&self) -> OwnedObjectPath {
+        match self.active_connection.ip6 {
+            Some(_) => ip6_config_path(self.index),
+            None => root_path(),
+        }
+    }
+}
+
+/// AddressData of an IP4Config or IP6Config
+fn address_data(ip_config: &MockIpConfig) -> Vec<HashMap<String, OwnedValue>> {
+    ip_config
+        .addresses
+        .iter()
+        .map(|(address, prefix)| {
+            HashMap::from([
+                (
+                    "address".to_string(),
+                    OwnedValue::from(zvariant::Str::from(address.clone())),
+                ),
+                ("prefix".to_string(), OwnedValue::from(*prefix)),
+            ])
+        })
+        .collect()
+}
+
+struct Ip4ConfigObject(MockIpConfig);
+
+#[dbus_interface(name = "org.freedesktop.NetworkManager.IP4Config")]
+impl Ip4ConfigObject {
+    #[dbus_interface(property)]
+    fn gateway(&self) -> String {
+        self.0.gateway.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn address_data(&self) -> Vec<HashMap<String, OwnedValue>> {
+        address_data(&self.0)
+    }
+}
+
+struct Ip6ConfigObject(MockIpConfig);
+
+#[dbus_interface(name = "org.freedesktop.NetworkManager.IP6Config")]
+impl Ip6ConfigObject {
+    #[dbus_interface(property)]
+    fn gateway(&self) -> String {
+        self.0.gateway.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn address_data(&self) -> Vec<HashMap<String, OwnedValue>> {
+        address_data(&self.0)
+    }
+}
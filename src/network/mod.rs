@@ -1,31 +1,598 @@
 mod access_point;
 mod active_connection;
+pub(crate) mod connectivity;
 mod device;
+mod dhcp4_config;
+pub(crate) mod hotspot;
 mod ip4_config;
+mod ip6_config;
+pub(crate) mod latency;
+#[cfg(test)]
+mod mock;
+pub(crate) mod modem;
 mod networkmanager;
+pub(crate) mod overview;
 mod ping;
+pub(crate) mod profiles;
+mod prompter;
+pub(crate) mod radio;
+mod rfkill;
+pub(crate) mod secret_agent;
+mod settings;
+#[cfg(test)]
+mod tests;
+pub(crate) mod traffic;
+pub(crate) mod vpn;
 
+use crate::error::{report, Error};
 use serde_json::json;
-use std::error::Error;
 use zbus::Connection;
 
-pub(crate) async fn info() -> Result<(), Box<dyn Error>> {
+pub(crate) async fn info(verbose: bool, probe: ProbeConfig) -> Result<(), Error> {
     let connection = Connection::system().await?;
+    watch_service(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        json!({ "state": "Unavailable" }),
+        || listen_info(&connection, verbose, &probe),
+    )
+    .await
+}
 
-    let nm = networkmanager::NetworkManager::new(&connection).await?;
+async fn listen_info(
+    connection: &Connection,
+    verbose: bool,
+    probe: &ProbeConfig,
+) -> Result<(), Error> {
+    let nm = networkmanager::NetworkManager::new(connection).await?;
+
+    // The access to internet is probed again only when the state of NetworkManager change,
+    // not on every strength or address update
+    let mut state_events = futures_util::stream::select_all(vec![
+        nm.proxy.receive_state_changed().await.map(|_| ()).boxed(),
+        nm.proxy
+            .receive_primary_connection_changed()
+            .await
+            .map(|_| ())
+            .boxed(),
+        nm.proxy
+            .receive_connectivity_changed()
+            .await
+            .map(|_| ())
+            .boxed(),
+    ]);
+    let mut events = futures_util::stream::select_all(vec![
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/Devices",
+            "org.freedesktop.NetworkManager.Device",
+            &["State"],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/AccessPoint",
+            "org.freedesktop.NetworkManager.AccessPoint",
+            &["Strength"],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/IP4Config",
+            "org.freedesktop.NetworkManager.IP4Config",
+            &[],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/IP6Config",
+            "org.freedesktop.NetworkManager.IP6Config",
+            &[],
+        )
+        .await?,
+    ]);
+
+    let mut probe_timer = tokio::time::interval(Duration::from_millis(probe.interval_ms));
+    probe_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    // Initial state, then every time something relevant change
+    let mut last_probe = None;
+    loop {
+        let reprobe = tokio::select! {
+            _ = probe_timer.tick() => true,
+            event = state_events.next() => match event {
+                Some(()) => true,
+                None => break,
+            },
+            event = events.next() => match event {
+                Some(()) => false,
+                None => break,
+            },
+        };
+        let probe_result = match (reprobe, last_probe) {
+            (false, Some(probe_result)) => probe_result,
+            _ => *last_probe.insert(connectivity::probe(probe, &nm).await),
+        };
+        report(print_info(&nm, verbose, probe_result).await)?;
+    }
+
+    Ok(())
+}
+
+async fn print_info(
+    nm: &networkmanager::NetworkManager<'_>,
+    verbose: bool,
+    probe_result: ProbeResult,
+) -> Result<(), Error> {
+    let state = nm.get_state().await?;
+    // Get currently used connection
+    let pc = nm.get_primary_connection().await?;
+    // retrive data
+    print_state(nm, pc, state, verbose, probe_result).await
+}
+
+/// Stream yielding each time an object under `path_namespace` emit a PropertiesChanged for `interface`.
+/// Only changes touching one of `properties` are kept, an empty slice keep every change.
+pub(super) async fn receive_properties_changed(
+    connection: &Connection,
+    path_namespace: &'static str,
+    interface: &'static str,
+    properties: &'static [&'static str],
+) -> Result<BoxStream<'static, ()>, Error> {
+    let rule = MatchRule::builder()
+        .msg_type(zbus::MessageType::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path_namespace(path_namespace)?
+        .add_arg(interface)?
+        .build();
+    let stream = MessageStream::for_match_rule(rule, connection, None)
+        .await?
+        .filter_map(move |msg| async move {
+            let signal = PropertiesChanged::from_message(msg.ok()?)?;
+            let args = signal.args().ok()?;
+            let changed = args.changed_properties();
+            (properties.is_empty() || properties.iter().any(|p| changed.contains_key(p)))
+                .then_some(())
+        });
+    Ok(stream.boxed())
+}
+
+/// Stream yielding each time an object under `path_namespace` emit the signal `member` of `interface`
+pub(super) async fn receive_signals(
+    connection: &Connection,
+    path_namespace: &'static str,
+    interface: &'static str,
+    member: &'static str,
+) -> Result<BoxStream<'static, ()>, Error> {
+    let rule = MatchRule::builder()
+        .msg_type(zbus::MessageType::Signal)
+        .interface(interface)?
+        .member(member)?
+        .path_namespace(path_namespace)?
+        .build();
+    let stream = MessageStream::for_match_rule(rule, connection, None)
+        .await?
+        .filter_map(|msg| async move { msg.ok().map(|_| ()) });
+    Ok(stream.boxed())
+}
+
+/// Time to wait before starting again a listener which stopped while its service is still there
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Run `listener` as long as `service` own its name on the bus.
+/// `unavailable` is printed while the service is gone, then `listener` is started again,
+/// rebuilding its proxies and streams, when the service comes back.
+pub(super) async fn watch_service<F, Fut>(
+    connection: &Connection,
+    service: &'static str,
+    unavailable: serde_json::Value,
+    mut listener: F,
+) -> Result<(), Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
+    let dbus = DBusProxy::new(connection).await?;
+    let mut owner_changed = dbus
+        .receive_name_owner_changed_with_args(&[(0, service)])
+        .await?;
+    loop {
+        if dbus.name_has_owner(service.try_into()?).await? {
+            let lost = async {
+                while let Some(signal) = owner_changed.next().await {
+                    if signal.args().is_ok_and(|args| args.new_owner().is_none()) {
+                        break;
+                    }
+                }
+            };
+            tokio::select! {
+                result = listener() => match result {
+                    // The service can leave in the middle of a query
+                    Ok(()) | Err(Error::ServiceMissing(_) | Error::ObjectVanished(_)) => {
+                        tokio::time::sleep(RESTART_DELAY).await
+                    }
+                    Err(error) => return Err(error),
+                },
+                _ = lost => {}
+            }
+        } else {
+            println!("{}", unavailable);
+            while let Some(signal) = owner_changed.next().await {
+                if signal.args().is_ok_and(|args| args.new_owner().is_some()) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Output a json array of every access point seen by the wireless devices whenever a change occur.
+/// When `grouped`, the access points of a same SSID are merged into the strongest one,
+/// with the BSSID of each one in `bssids`.
+/// `[]` is output while NetworkManager is not running.
+///
+/// ``` json
+/// [
+///   {
+///     "ssid": "home",
+///     "ssidHex": "686f6d65",
+///     "hidden": false,
+///     "bssid": "AA:BB:CC:DD:EE:FF",
+///     "interface": "wlan0",
+///     "strength": 74,
+///     "frequency": 5180,
+///     "maxBitrate": 540000,
+///     "mode": "Infra",
+///     "lastSeen": 5412,
+///     "security": "wpa2-psk",
+///     "flags": 1,
+///     "wpaFlags": 0,
+///     "rsnFlags": 392,
+///     "active": true,
+///     "saved": true
+///   }
+/// ]
+/// ```
+pub(crate) async fn list(grouped: bool) -> Result<(), Error> {
+    let connection = Connection::system().await?;
+    watch_service(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        json!([]),
+        || listen_access_points(&connection, grouped),
+    )
+    .await
+}
+
+async fn listen_access_points(connection: &Connection, grouped: bool) -> Result<(), Error> {
+    let nm = networkmanager::NetworkManager::new(connection).await?;
+    let settings = settings::Settings::new(connection).await?;
 
-    let mut prop_stream = nm.proxy.receive_state_changed().await;
-    while let Some(prop_changed) = prop_stream.next().await {
-        let state = prop_changed.get().await?;
-        // Get currently used connection
-        let pc = nm.get_primary_connection().await?;
-        // retrive data
-        print_state(pc, state).await?;
+    // Hot-plugged adapters are only seen by listing the wireless devices again
+    let mut devices_events = futures_util::stream::select_all(vec![
+        nm.proxy.receive_device_added().await?.map(|_| ()).boxed(),
+        nm.proxy.receive_device_removed().await?.map(|_| ()).boxed(),
+    ]);
+    // Reading the SSID of every profile is costly, it is only done again when one is added,
+    // removed or updated
+    let mut settings_events = futures_util::stream::select_all(vec![
+        settings.receive_new_connection().await?.map(|_| ()).boxed(),
+        settings
+            .receive_connection_removed()
+            .await?
+            .map(|_| ())
+            .boxed(),
+        receive_signals(
+            connection,
+            "/org/freedesktop/NetworkManager/Settings",
+            "org.freedesktop.NetworkManager.Settings.Connection",
+            "Updated",
+        )
+        .await?,
+    ]);
+
+    let mut saved_ssids = vec![];
+    let mut refresh_saved = true;
+    loop {
+        let wireless_devices = nm.get_wireless_devices().await?;
+        let mut events = vec![];
+        for device in &wireless_devices {
+            events.push(device.receive_access_point_added().await?.map(|_| ()).boxed());
+            events.push(device.receive_access_point_removed().await?.map(|_| ()).boxed());
+            events.push(
+                device
+                    .receive_active_access_point_changed()
+                    .await
+                    .map(|_| ())
+                    .boxed(),
+            );
+        }
+        events.push(
+            receive_properties_changed(
+                connection,
+                "/org/freedesktop/NetworkManager/AccessPoint",
+                "org.freedesktop.NetworkManager.AccessPoint",
+                &["Strength"],
+            )
+            .await?,
+        );
+        let mut events = futures_util::stream::select_all(events);
+
+        loop {
+            report(
+                async {
+                    if refresh_saved {
+                        saved_ssids = settings.get_saved_ssids().await?;
+                        refresh_saved = false;
+                    }
+                    print_access_points(&wireless_devices, &saved_ssids, grouped).await
+                }
+                .await,
+            )?;
+            tokio::select! {
+                event = devices_events.next() => match event {
+                    Some(()) => break,
+                    None => return Ok(()),
+                },
+                event = settings_events.next() => match event {
+                    Some(()) => refresh_saved = true,
+                    None => return Ok(()),
+                },
+                event = events.next() => match event {
+                    Some(()) => (),
+                    None => return Ok(()),
+                },
+            }
+        }
     }
+}
 
+/// Print the access points seen by `wireless_devices`, `saved_ssids` being the SSIDs of the
+/// saved profiles
+async fn print_access_points(
+    wireless_devices: &[device::WirelessDevice<'_>],
+    saved_ssids: &[String],
+    grouped: bool,
+) -> Result<(), Error> {
+    let mut access_points = vec![];
+    for device in wireless_devices {
+        let interface = device.get_interface().await?;
+        let active_path = device.get_active_access_point().await?.get_path().clone();
+        for access_point in device.get_access_points().await? {
+            // Access points can vanish between the listing and the property reads
+            let Ok(ssid_raw) = access_point.get_ssid_raw().await else {
+                continue;
+            };
+            let ssid = access_point::ssid_to_string(&ssid_raw);
+            let Ok((strength, frequency, max_bitrate, flags, wpa_flags, rsn_flags)) = tokio::try_join!(
+                access_point.get_strength(),
+                access_point.get_frequency(),
+                access_point.get_max_bitrate(),
+                access_point.get_flags(),
+                access_point.get_wpa_flags(),
+                access_point.get_rsn_flags(),
+            ) else {
+                continue;
+            };
+            let Ok((bssid, mode, last_seen)) = tokio::try_join!(
+                access_point.get_hw_address(),
+                access_point.get_mode(),
+                access_point.get_last_seen(),
+            ) else {
+                continue;
+            };
+            let security = access_point::Security::from_flags(flags, wpa_flags, rsn_flags);
+            access_points.push(json!({
+                "active": *access_point.get_path() == active_path,
+                "saved": saved_ssids.contains(&ssid),
+                "ssid": ssid,
+                "ssidHex": access_point::ssid_to_hex(&ssid_raw),
+                "hidden": access_point::is_hidden_ssid(&ssid_raw),
+                "bssid": bssid,
+                "interface": interface,
+                "strength": strength,
+                "frequency": frequency,
+                "maxBitrate": max_bitrate,
+                "mode": mode.to_string(),
+                "lastSeen": last_seen,
+                "security": security.name(),
+                "flags": flags,
+                "wpaFlags": wpa_flags,
+                "rsnFlags": rsn_flags,
+            }));
+        }
+    }
+    access_points.sort_by_key(|access_point| {
+        std::cmp::Reverse(access_point["strength"].as_u64().unwrap_or_default())
+    });
+    if grouped {
+        access_points = group_access_points(access_points);
+    }
+    println!("{}", serde_json::Value::Array(access_points));
     Ok(())
 }
 
+/// Merge the access points sharing an SSID (mesh nodes, bands of the same router)
+/// into the strongest one, which lists every BSSID of the network.
+/// `access_points` must be sorted by strength.
+fn group_access_points(access_points: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
+    let mut networks: Vec<serde_json::Value> = vec![];
+    for access_point in access_points {
+        let bssid = access_point["bssid"].clone();
+        let active = access_point["active"].as_bool().unwrap_or_default();
+        // Hidden networks can not be told apart
+        let network = networks.iter_mut().find(|network| {
+            network["ssidHex"] == access_point["ssidHex"] && access_point["hidden"] == false
+        });
+        match network {
+            Some(network) => {
+                network["bssids"]
+                    .as_array_mut()
+                    .expect("bssids is an array")
+                    .push(bssid);
+                if active {
+                    network["active"] = json!(true);
+                }
+            }
+            None => {
+                let mut network = access_point;
+                network["bssids"] = json!([bssid]);
+                networks.push(network);
+            }
+        }
+    }
+    networks
+}
+
+/// Scan for access points on the wireless device `interface`, or on every one,
+/// then output the json array of [`list`] once the scans are done or after `timeout` milliseconds
+pub(crate) async fn scan(
+    interface: Option<&str>,
+    timeout: u64,
+    grouped: bool,
+) -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let nm = networkmanager::NetworkManager::new(&connection).await?;
+    let settings = settings::Settings::new(&connection).await?;
+    let mut wireless_devices = vec![];
+    for device in nm.get_wireless_devices().await? {
+        if interface.is_none() || Some(device.get_interface().await?.as_str()) == interface {
+            wireless_devices.push(device);
+        }
+    }
+    if wireless_devices.is_empty() {
+        return Err(match interface {
+            Some(interface) => format!("no wireless device named \"{}\"", interface).into(),
+            None => "no wireless device found".into(),
+        });
+    }
+
+    let mut scans = vec![];
+    for device in &wireless_devices {
+        // Subscribe before the request so that a fast scan is not missed
+        let mut last_scan_changed = device.receive_last_scan_changed().await;
+        let last_scan = device.get_last_scan().await?;
+        // A refused request means a scan just happened or is running, wait for it anyway
+        let _ = device.request_scan().await;
+        scans.push(async move {
+            while let Some(change) = last_scan_changed.next().await {
+                if change.get().await.is_ok_and(|scan| scan != last_scan) {
+                    break;
+                }
+            }
+        });
+    }
+    // The access points found so far are printed when a device takes too long
+    let _ = tokio::time::timeout(
+        std::time::Duration::from_millis(timeout),
+        futures_util::future::join_all(scans),
+    )
+    .await;
+
+    let saved_ssids = settings.get_saved_ssids().await?;
+    print_access_points(&wireless_devices, &saved_ssids, grouped).await
+}
+
+/// Connect to the Wi-Fi network `ssid`, using its saved profile when one exist,
+/// with `password` replacing the saved one. A new profile is secured as the access point require.
+/// A `hidden` network is probed for by its SSID instead of being looked up in the scans.
+/// The connection is made with the wireless device `interface`, or the first one.
+/// Output a json each time the state of the activation change
+///
+/// ``` json
+/// {"ssid": "home", "state": "Activating"}
+/// {"ssid": "home", "state": "Activated"}
+/// ```
+pub(crate) async fn connect(
+    ssid: &str,
+    password: Option<&str>,
+    hidden: bool,
+    interface: Option<&str>,
+) -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let nm = networkmanager::NetworkManager::new(&connection).await?;
+    let settings = settings::Settings::new(&connection).await?;
+    let mut device = None;
+    for candidate in nm.get_wireless_devices().await? {
+        if interface.is_none() || Some(candidate.get_interface().await?.as_str()) == interface {
+            device = Some(candidate);
+            break;
+        }
+    }
+    let device = device.ok_or_else(|| match interface {
+        Some(interface) => format!("no wireless device named \"{}\"", interface),
+        None => "no wireless device found".to_string(),
+    })?;
+
+    let active_connection = match settings.get_connection_by_ssid(ssid).await? {
+        Some(saved) => {
+            if let Some(password) = password {
+                saved.set_wireless_password(password).await?;
+            }
+            nm.activate_connection(saved.get_path(), device.path(), "/")
+                .await?
+        }
+        None => {
+            let mut specific_object = "/".to_string();
+            // Only unknown for a hidden network or one out of range, WPA-PSK is then assumed
+            let mut security = None;
+            // Hidden networks show up without their SSID
+            let access_points = match hidden {
+                true => vec![],
+                false => device.get_access_points().await?,
+            };
+            for access_point in access_points {
+                if access_point.get_ssid().await.is_ok_and(|ap_ssid| ap_ssid == ssid) {
+                    specific_object = access_point.get_path().clone();
+                    security = Some(access_point.get_security().await?);
+                    break;
+                }
+            }
+            nm.add_and_activate_connection(
+                settings::wireless_settings(ssid, password, hidden, security)?,
+                device.path(),
+                &specific_object,
+            )
+            .await?
+        }
+    };
+
+    follow_activation(&active_connection, "ssid", ssid).await
+}
+
+/// Print `{<key>: <name>, "state": ...}` on each state change of `active_connection`
+/// until it is activated, or fail with the reason of the deactivation.
+async fn follow_activation(
+    active_connection: &active_connection::ActiveConnection<'_>,
+    key: &str,
+    name: &str,
+) -> Result<(), Error> {
+    let mut state_changed = active_connection.receive_state_changed().await?;
+    let mut state = active_connection.get_state().await?;
+    let mut reason = NMActiveConnectionStateReason::None;
+    loop {
+        println!("{}", json!({ key: name, "state": state.to_string() }));
+        match state {
+            NMActiveConnectionState::ActivatedConnection => return Ok(()),
+            NMActiveConnectionState::DeactivatedConnection if reason.is_auth_failure() => {
+                return Err(format!("authentication failed for \"{}\" ({})", name, reason).into())
+            }
+            NMActiveConnectionState::DeactivatedConnection => {
+                return Err(format!("failed to connect to \"{}\" ({})", name, reason).into())
+            }
+            _ => (),
+        }
+        let msg = state_changed
+            .next()
+            .await
+            .ok_or("active connection vanished")?;
+        (state, reason) = active_connection::parse_state_changed(&msg)?;
+    }
+}
+
 enum Conn<'a> {
     Asleep,
     Connected {
@@ -53,22 +620,96 @@ enum Conn<'a> {
 }
 
 // impl<'a> TryFrom for Conn<'a> {
-//     type Error = Box<dyn Error>;
+//     type Error = Error;
 
 //     fn try_from(value: T) -> Result<Self, Self::Error> {
 //         todo!()
 //     }
 // }
 
+/// DNS, routes and DHCP details of the IPv4 configuration, for `info --verbose`
+async fn ip4_details(
+    pc: &active_connection::ActiveConnection<'_>,
+) -> Result<serde_json::Value, Error> {
+    let pc_config = pc.get_ip4_config().await?;
+    let (nameservers, routes, searches, domains, wins_servers) = tokio::try_join!(
+        pc_config.get_nameservers(),
+        pc_config.get_routes(),
+        pc_config.get_searches(),
+        pc_config.get_domains(),
+        pc_config.get_wins_servers(),
+    )?;
+    let routes = routes
+        .iter()
+        .map(|route| {
+            json!({
+                "dest": route.dest,
+                "prefix": route.prefix,
+                "nextHop": route.next_hop,
+                "metric": route.metric,
+            })
+        })
+        .collect::<Vec<_>>();
+    // No DHCP4Config for static configurations
+    let (lease_time, dhcp_server) = match pc.get_dhcp4_config().await {
+        Ok(dhcp_config) => (
+            dhcp_config.get_lease_time().await.unwrap_or_default(),
+            dhcp_config.get_server().await.unwrap_or_default(),
+        ),
+        Err(_) => (None, None),
+    };
+
+    Ok(json!({
+        "nameservers": nameservers,
+        "routes": routes,
+        "searches": searches,
+        "domains": domains,
+        "winsServers": wins_servers,
+        "dhcpLeaseTime": lease_time, // In seconds, null without DHCP
+        "dhcpServer": dhcp_server, // null without DHCP
+    }))
+}
+
 async fn print_state(
+    nm: &networkmanager::NetworkManager<'_>,
     pc: active_connection::ActiveConnection<'_>,
     state: NMState,
-) -> Result<(), Box<dyn Error>> {
+    verbose: bool,
+    probe_result: ProbeResult,
+) -> Result<(), Error> {
+    println!("{}", state_info(nm, pc, state, verbose, probe_result).await?);
+    Ok(())
+}
+
+/// Json printed by [`info`] for the primary connection `pc`, `probe_result` being the last
+/// check of the access to internet
+async fn state_info(
+    nm: &networkmanager::NetworkManager<'_>,
+    pc: active_connection::ActiveConnection<'_>,
+    state: NMState,
+    verbose: bool,
+    probe_result: ProbeResult,
+) -> Result<serde_json::Value, Error> {
+    // NetworkManager reports a Portal connectivity when its check got redirected, the prober only
+    // sees a failed request
+    let captive_portal = nm
+        .get_connectivity()
+        .await
+        .is_ok_and(|connectivity| connectivity == NMConnectivityState::Portal);
+    let check_url = match captive_portal {
+        true => nm
+            .get_connectivity_check_uri()
+            .await
+            .ok()
+            .filter(|uri| !uri.is_empty()),
+        false => None,
+    };
+
     match tokio::join!(
-        is_connected_to_internet(),
         async {
             let pc_devices = pc.get_devices().await?;
-            let pc_device = pc_devices.first().unwrap();
+            // No device when there is no primary connection
+            let pc_device = pc_devices.first().ok_or("primary connection without device")?;
 
             futures_util::future::try_join(pc_device.get_device_data(), pc_device.get_interface())
                 .await
@@ -77,11 +718,31 @@ async fn print_state(
             let pc_config = pc.get_ip4_config().await?;
             futures_util::future::try_join(pc_config.get_gateway(), pc_config.get_addresses()).await
         },
+        async {
+            let pc_config = pc.get_ip6_config().await?;
+            futures_util::future::try_join(pc_config.get_gateway(), pc_config.get_addresses()).await
+        },
     ) {
-        (global_conn, Ok((d_data, interface)), Ok((gateway, pc_config_addresses))) => {
-            let pc_config_address = pc_config_addresses.first().unwrap();
-            let ipaddr = &pc_config_address.address;
-            let cidr = &pc_config_address.mask_cird;
+        (Ok((d_data, interface)), ip4, ip6) => {
+            let global_conn = probe_result.connected && !captive_portal;
+            // A connection can be IPv4 only or IPv6 only
+            let (gateway, ipaddr, cidr) = match ip4 {
+                Ok((gateway, addresses)) => match addresses.into_iter().next() {
+                    Some(address) => (gateway, address.address, address.mask_cird),
+                    None => (gateway, "".to_string(), "".to_string()),
+                },
+                Err(_) => Default::default(),
+            };
+            let (ip6_gateway, ip6_addr, ip6_cidr) = match ip6 {
+                // Prefer a routable address over the link-local one
+                Ok((gateway, addresses)) => {
+                    match addresses.into_iter().min_by_key(|address| address.is_link_local()) {
+                        Some(address) => (gateway, address.address, address.mask_cird),
+                        None => (gateway, "".to_string(), "".to_string()),
+                    }
+                }
+                Err(_) => Default::default(),
+            };
             let (kind, frequency, signal_strength, ssid) = d_data;
 
             let state = if NMState::ConnectedGlobal == state && !global_conn {
@@ -89,104 +750,50 @@ async fn print_state(
             } else {
                 state
             };
-            let icon_path = icon_path(state, global_conn, signal_strength);
+            let icon_path = icon_path(state, global_conn, captive_portal, signal_strength);
             
-            let info = json!({
+            let mut info = json!({
                 "state": state.to_string(), // Connecting, Disconnected, Disconnecting, Connected_Global, Connected_Local,
                 "interface": interface, // Current interface/Device
                 "kind": kind, // Wireless, Wired
-                "ssid": ssid, // Only Wireless
+                "ssid": access_point::ssid_to_string(&ssid), // Only Wireless
+                "ssidHex": access_point::ssid_to_hex(&ssid), // Only Wireless
                 "signalStrength": signal_strength, // Only Wireless
                 "frequency": frequency, // Only Wireless
                 "ipaddr": ipaddr, // Wireless & Wirer
                 "cidr": cidr, // Wireless & Wired
                 "gateway": gateway, // Wireless & Wired
+                "ip6addr": ip6_addr, // Wireless & Wired
+                "ip6cidr": ip6_cidr, // Wireless & Wired
+                "ip6gateway": ip6_gateway, // Wireless & Wired
                 "icon": icon_path, // At any Time
+                "latency": probe_result.latency_ms(), // In ms, null when not measured
+                "captivePortal": captive_portal, // At any Time
+                "checkUrl": check_url, // Connectivity check endpoint, only behind a captive portal
             });
-            Ok(println!("{}", info))
-        }
-        (_, _, _) => {
-            Ok(println!(
-                "{}",
-                json!({
-                    "state": state.to_string(), // Connecting, Connected, Disconnected, Disconnecting, ConnectedGlobal, ConnectedLocal, Asleep
-                })
-            ))
+            if verbose {
+                if let (Some(info), Ok(serde_json::Value::Object(details))) =
+                    (info.as_object_mut(), ip4_details(&pc).await)
+                {
+                    info.extend(details);
+                }
+            }
+            Ok(info)
         }
+        (_, _, _) => Ok(json!({
+            "state": state.to_string(), // Connecting, Connected, Disconnected, Disconnecting, ConnectedGlobal, ConnectedLocal, Asleep
+        })),
     }
 }
-use async_io::Timer;
-use futures_util::{
-    future::select,
-    future::Either::{Left, Right},
-    pin_mut, StreamExt, TryStreamExt,
+use futures_util::{stream::BoxStream, Future, StreamExt};
+use std::time::Duration;
+use zbus::{
+    fdo::{DBusProxy, PropertiesChanged},
+    MatchRule, MessageStream,
 };
-use zbus::{fdo::NameOwnerChanged, AsyncDrop, MatchRule, MessageStream};
 
 use self::{
-    networkmanager::{icon_path, NMState},
-    ping::is_connected_to_internet,
+    active_connection::{NMActiveConnectionState, NMActiveConnectionStateReason},
+    networkmanager::{icon_path, NMConnectivityState, NMState},
+    connectivity::{ProbeConfig, ProbeResult},
 };
-
-pub async fn test() -> Result<(), Box<dyn Error>> {
-    let conn = Connection::session().await?;
-    let rule = MatchRule::builder()
-        .msg_type(zbus::MessageType::Signal)
-        .sender("org.freedesktop.DBus")?
-        .interface("org.freedesktop.DBus")?
-        .member("NameOwnerChanged")?
-        .add_arg("org.freedesktop.zbus.MatchRuleStreamTest42")?
-        .build();
-    let mut stream = MessageStream::for_match_rule(
-        rule,
-        &conn,
-        // For such a specific match rule, we don't need a big queue.
-        Some(1),
-    )
-    .await?;
-
-    let rule_str = "type='signal',sender='org.freedesktop.DBus',\
-                interface='org.freedesktop.DBus',member='NameOwnerChanged',\
-                arg0='org.freedesktop.zbus.MatchRuleStreamTest42'";
-    assert_eq!(
-        stream.match_rule().map(|r| r.to_string()).as_deref(),
-        Some(rule_str),
-    );
-
-    // We register 2 names, starting with the uninteresting one. If `stream` wasn't filtering
-    // messages based on the match rule, we'd receive method return call for each of these 2
-    // calls first.
-    //
-    // Note that the `NameOwnerChanged` signal will not be sent by the bus  for the first name
-    // we register since we setup an arg filter.
-    conn.request_name("org.freedesktop.zbus.MatchRuleStreamTest44")
-        .await?;
-    conn.request_name("org.freedesktop.zbus.MatchRuleStreamTest42")
-        .await?;
-
-    let msg = stream.try_next().await?.unwrap();
-    let signal = NameOwnerChanged::from_message(msg).unwrap();
-    assert_eq!(
-        signal.args()?.name(),
-        "org.freedesktop.zbus.MatchRuleStreamTest42"
-    );
-    stream.async_drop().await;
-
-    // Ensure the match rule is deregistered and this connection doesn't receive
-    // `NameOwnerChanged` signals.
-    let stream = MessageStream::from(&conn)
-        .try_filter_map(|msg| async move { Ok(NameOwnerChanged::from_message(msg)) });
-    conn.release_name("org.freedesktop.zbus.MatchRuleStreamTest42")
-        .await?;
-
-    pin_mut!(stream);
-    let next = stream.try_next();
-    pin_mut!(next);
-    let timeout = Timer::after(std::time::Duration::from_millis(50));
-    pin_mut!(timeout);
-    match select(next, timeout).await {
-        Left((msg, _)) => unreachable!("unexpected message: {:?}", msg),
-        Right((_, _)) => (),
-    };
-    Ok(())
-}
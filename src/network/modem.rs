@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use zbus::{Connection, MatchRule, MessageStream, Proxy};
+use zvariant::{OwnedObjectPath, OwnedValue};
+
+use super::{
+    device::Device, networkmanager::NetworkManager, receive_properties_changed, watch_service,
+};
+use crate::error::{report, Error};
+
+// Not an OwnedValue, which does not support the negative `Failed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MMModemState {
+    /// The modem is unusable
+    Failed = -1,
+    /// State unknown or not reportable
+    Unknown = 0,
+    /// The modem is currently being initialized
+    Initializing = 1,
+    /// The modem needs to be unlocked
+    Locked = 2,
+    /// The modem is not enabled and is powered down
+    Disabled = 3,
+    /// The modem is currently transitioning to the Disabled state
+    Disabling = 4,
+    /// The modem is currently transitioning to the Enabled state
+    Enabling = 5,
+    /// The modem is enabled and powered on but not registered with a network provider
+    Enabled = 6,
+    /// The modem is searching for a network provider to register with
+    Searching = 7,
+    /// The modem is registered with a network provider, and data connections and messaging may be available for use
+    Registered = 8,
+    /// The modem is disconnecting and deactivating the last active packet data bearer
+    Disconnecting = 9,
+    /// The modem is activating and connecting the first packet data bearer
+    Connecting = 10,
+    /// One or more packet data bearers is active and connected
+    Connected = 11,
+}
+
+impl From<i32> for MMModemState {
+    fn from(state: i32) -> MMModemState {
+        match state {
+            -1 => MMModemState::Failed,
+            1 => MMModemState::Initializing,
+            2 => MMModemState::Locked,
+            3 => MMModemState::Disabled,
+            4 => MMModemState::Disabling,
+            5 => MMModemState::Enabling,
+            6 => MMModemState::Enabled,
+            7 => MMModemState::Searching,
+            8 => MMModemState::Registered,
+            9 => MMModemState::Disconnecting,
+            10 => MMModemState::Connecting,
+            11 => MMModemState::Connected,
+            _ => MMModemState::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for MMModemState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, OwnedValue, Clone, Copy, PartialEq, Eq)]
+pub enum MMModem3gppRegistrationState {
+    /// Not registered, not searching for new operator to register
+    Idle = 0,
+    /// Registered on home network
+    Home = 1,
+    /// Not registered, searching for new operator to register with
+    Searching = 2,
+    /// Registration denied
+    Denied = 3,
+    /// Unknown registration status
+    Unknown = 4,
+    /// Registered on a roaming network
+    Roaming = 5,
+    /// Registered for "SMS only", home network
+    HomeSmsOnly = 6,
+    /// Registered for "SMS only", roaming network
+    RoamingSmsOnly = 7,
+    /// Emergency services only
+    EmergencyOnly = 8,
+    /// Registered for "CSFB not preferred", home network
+    HomeCsfbNotPreferred = 9,
+    /// Registered for "CSFB not preferred", roaming network
+    RoamingCsfbNotPreferred = 10,
+    /// Attached for access to Restricted Local Operator Services
+    AttachedRlos = 11,
+}
+
+impl MMModem3gppRegistrationState {
+    pub fn is_roaming(&self) -> bool {
+        matches!(
+            self,
+            MMModem3gppRegistrationState::Roaming
+                | MMModem3gppRegistrationState::RoamingSmsOnly
+                | MMModem3gppRegistrationState::RoamingCsfbNotPreferred
+        )
+    }
+}
+
+#[derive(Debug, OwnedValue, Clone, Copy, PartialEq, Eq)]
+pub enum MMModemLock {
+    /// Lock reason unknown
+    Unknown = 0,
+    /// Modem is unlocked
+    None = 1,
+    /// SIM requires the PIN code
+    SimPin = 2,
+    /// SIM requires the PIN2 code
+    SimPin2 = 3,
+    /// SIM requires the PUK code
+    SimPuk = 4,
+    /// SIM requires the PUK2 code
+    SimPuk2 = 5,
+    /// Modem requires the service provider PIN code
+    PhSpPin = 6,
+    /// Modem requires the service provider PUK code
+    PhSpPuk = 7,
+    /// Modem requires the network PIN code
+    PhNetPin = 8,
+    /// Modem requires the network PUK code
+    PhNetPuk = 9,
+    /// Modem requires the PIN code
+    PhSimPin = 10,
+    /// Modem requires the corporate PIN code
+    PhCorpPin = 11,
+    /// Modem requires the corporate PUK code
+    PhCorpPuk = 12,
+    /// Modem requires the PH-FSIM PIN code
+    PhFsimPin = 13,
+    /// Modem requires the PH-FSIM PUK code
+    PhFsimPuk = 14,
+    /// Modem requires the network subset PIN code
+    PhNetsubPin = 15,
+    /// Modem requires the network subset PUK code
+    PhNetsubPuk = 16,
+}
+
+/// Names of the MMModemAccessTechnology flags, from the slowest to the fastest
+const ACCESS_TECHNOLOGIES: [(u32, &str); 18] = [
+    (0x1, "pots"),
+    (0x2, "gsm"),
+    (0x4, "gsm-compact"),
+    (0x8, "gprs"),
+    (0x10, "edge"),
+    (0x400, "1xrtt"),
+    (0x20, "umts"),
+    (0x800, "evdo0"),
+    (0x1000, "evdoa"),
+    (0x2000, "evdob"),
+    (0x40, "hsdpa"),
+    (0x80, "hsupa"),
+    (0x100, "hspa"),
+    (0x200, "hspa+"),
+    (0x20000, "lte-nb-iot"),
+    (0x10000, "lte-cat-m"),
+    (0x4000, "lte"),
+    (0x8000, "5gnr"),
+];
+
+/// Names of every technology set in a MMModemAccessTechnology bitmask
+pub fn access_technologies(flags: u32) -> Vec<&'static str> {
+    ACCESS_TECHNOLOGIES
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+pub struct ModemManager<'a> {
+    /// Proxy pointing on "org.freedesktop.DBus.ObjectManager"
+    proxy: Proxy<'a>,
+    connection: &'a Connection,
+}
+
+impl ModemManager<'_> {
+    pub async fn new(connection: &Connection) -> Result<ModemManager<'_>, Error> {
+        let p = Proxy::new(
+            connection,
+            "org.freedesktop.ModemManager1",
+            "/org/freedesktop/ModemManager1",
+            "org.freedesktop.DBus.ObjectManager",
+        )
+        .await?;
+        Ok(ModemManager {
+            proxy: p,
+            connection,
+        })
+    }
+
+    pub async fn get_modems(&self) -> Result<Vec<Modem<'_>>, Error> {
+        let objects: HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>> =
+            self.proxy.call("GetManagedObjects", &()).await?;
+        let mut modems = vec![];
+        for (path, interfaces) in objects {
+            if interfaces.contains_key("org.freedesktop.ModemManager1.Modem") {
+                modems.push(Modem::new(path, self.connection).await?);
+            }
+        }
+        // Modems are numbered in the order they were found
+        modems.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(modems)
+    }
+}
+
+pub struct Modem<'a> {
+    path: String,
+    /// Proxy pointing on "org.freedesktop.ModemManager1.Modem"
+    proxy: Proxy<'a>,
+    /// Proxy pointing on "org.freedesktop.ModemManager1.Modem.Modem3gpp"
+    proxy_3gpp: Proxy<'a>,
+}
+
+impl Modem<'_> {
+    pub async fn new(path: OwnedObjectPath, connection: &Connection) -> Result<Modem<'_>, Error> {
+        let proxy = Proxy::new(
+            connection,
+            "org.freedesktop.ModemManager1",
+            path.clone(),
+            "org.freedesktop.ModemManager1.Modem",
+        )
+        .await?;
+        let proxy_3gpp = Proxy::new(
+            connection,
+            "org.freedesktop.ModemManager1",
+            path.clone(),
+            "org.freedesktop.ModemManager1.Modem.Modem3gpp",
+        )
+        .await?;
+        Ok(Modem {
+            path: path.to_string(),
+            proxy,
+            proxy_3gpp,
+        })
+    }
+
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    pub async fn get_manufacturer(&self) -> Result<String, Error> {
+        Ok(self.proxy.get_property::<String>("Manufacturer").await?)
+    }
+
+    pub async fn get_model(&self) -> Result<String, Error> {
+        Ok(self.proxy.get_property::<String>("Model").await?)
+    }
+
+    pub async fn get_state(&self) -> Result<MMModemState, Error> {
+        Ok(self.proxy.get_property::<i32>("State").await?.into())
+    }
+
+    /// Bitmask of the access technologies currently in use (MMModemAccessTechnology)
+    pub async fn get_access_technologies(&self) -> Result<u32, Error> {
+        Ok(self.proxy.get_property::<u32>("AccessTechnologies").await?)
+    }
+
+    /// Signal quality in percent, and whether the value was recently taken
+    pub async fn get_signal_quality(&self) -> Result<(u32, bool), Error> {
+        Ok(self
+            .proxy
+            .get_property::<(u32, bool)>("SignalQuality")
+            .await?)
+    }
+
+    /// Object path of the SIM card, "/" when there is none
+    pub async fn get_sim_path(&self) -> Result<OwnedObjectPath, Error> {
+        Ok(self.proxy.get_property::<OwnedObjectPath>("Sim").await?)
+    }
+
+    /// The code needed to unlock the modem
+    pub async fn get_unlock_required(&self) -> Result<MMModemLock, Error> {
+        Ok(self
+            .proxy
+            .get_property::<MMModemLock>("UnlockRequired")
+            .await?)
+    }
+
+    /// Name of the operator the modem is registered on
+    pub async fn get_operator_name(&self) -> Result<String, Error> {
+        Ok(self
+            .proxy_3gpp
+            .get_property::<String>("OperatorName")
+            .await?)
+    }
+
+    /// MCC and MNC of the operator the modem is registered on
+    pub async fn get_operator_code(&self) -> Result<String, Error> {
+        Ok(self
+            .proxy_3gpp
+            .get_property::<String>("OperatorCode")
+            .await?)
+    }
+
+    pub async fn get_registration_state(&self) -> Result<MMModem3gppRegistrationState, Error> {
+        Ok(self
+            .proxy_3gpp
+            .get_property::<MMModem3gppRegistrationState>("RegistrationState")
+            .await?)
+    }
+
+    /// "missing", "locked" or "ready"
+    pub async fn get_sim_state(&self) -> Result<&'static str, Error> {
+        if self.get_sim_path().await?.as_str() == "/" {
+            return Ok("missing");
+        }
+        match self.get_unlock_required().await? {
+            MMModemLock::None | MMModemLock::Unknown => Ok("ready"),
+            _ => Ok("locked"),
+        }
+    }
+}
+
+/// Output a json array of the cellular modems whenever one of them change.
+/// `interface` and `nmState` come from the matching NetworkManager modem device,
+/// and are null when NetworkManager does not manage the modem.
+/// `[]` is output while ModemManager is not running.
+///
+/// ``` json
+/// [
+///   {
+///     "path": "/org/freedesktop/ModemManager1/Modem/0",
+///     "interface": "cdc-wdm0",
+///     "nmState": "Activated",
+///     "manufacturer": "Quectel",
+///     "model": "EM12-G",
+///     "state": "Connected",
+///     "operator": "Orange F",
+///     "operatorCode": "20801",
+///     "accessTechnology": "lte",
+///     "accessTechnologies": ["lte"],
+///     "signalQuality": 74,
+///     "simState": "ready",
+///     "roaming": false
+///   }
+/// ]
+/// ```
+pub(crate) async fn listener() -> Result<(), Error> {
+    let connection = Connection::system().await?;
+    watch_service(
+        &connection,
+        "org.freedesktop.ModemManager1",
+        json!([]),
+        || listen(&connection),
+    )
+    .await
+}
+
+async fn listen(connection: &Connection) -> Result<(), Error> {
+    let mm = ModemManager::new(connection).await?;
+    let nm = NetworkManager::new(connection).await?;
+
+    let rule = MatchRule::builder()
+        .msg_type(zbus::MessageType::Signal)
+        .interface("org.freedesktop.DBus.ObjectManager")?
+        .path("/org/freedesktop/ModemManager1")?
+        .build();
+    let mut events = futures_util::stream::select_all(vec![
+        MessageStream::for_match_rule(rule, connection, None)
+            .await?
+            .map(|_| ())
+            .boxed(),
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/ModemManager1/Modem",
+            "org.freedesktop.ModemManager1.Modem",
+            &[
+                "State",
+                "AccessTechnologies",
+                "SignalQuality",
+                "Sim",
+                "UnlockRequired",
+            ],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/ModemManager1/Modem",
+            "org.freedesktop.ModemManager1.Modem.Modem3gpp",
+            &["OperatorName", "OperatorCode", "RegistrationState"],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            &["Devices"],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/Devices",
+            "org.freedesktop.NetworkManager.Device",
+            &["State"],
+        )
+        .await?,
+    ]);
+
+    report(print_modems(&mm, &nm).await)?;
+    while events.next().await.is_some() {
+        report(print_modems(&mm, &nm).await)?;
+    }
+
+    Ok(())
+}
+
+async fn print_modems(mm: &ModemManager<'_>, nm: &NetworkManager<'_>) -> Result<(), Error> {
+    // The Udi of a NetworkManager modem device is the path of the ModemManager modem
+    // NetworkManager is optional, the modems are then output without its data
+    let mut nm_modems = HashMap::new();
+    for device in nm.get_devices().await.unwrap_or_default() {
+        if let Device::ModemDevice(_) = device {
+            // Devices can vanish between the listing and the property reads
+            if let Ok((udi, interface, state)) =
+                tokio::try_join!(device.get_udi(), device.get_interface(), device.get_state())
+            {
+                nm_modems.insert(udi, (interface, format!("{:?}", state)));
+            }
+        }
+    }
+
+    let mut modems = vec![];
+    for modem in mm.get_modems().await? {
+        if let Ok(mut data) = modem_data(&modem).await {
+            let (interface, nm_state) = match nm_modems.remove(modem.get_path()) {
+                Some((interface, state)) => (Some(interface), Some(state)),
+                None => (None, None),
+            };
+            data["interface"] = json!(interface);
+            data["nmState"] = json!(nm_state);
+            modems.push(data);
+        }
+    }
+    println!("{}", Value::Array(modems));
+    Ok(())
+}
+
+async fn modem_data(modem: &Modem<'_>) -> Result<Value, Error> {
+    let (manufacturer, model, state, access_technologies_flags, (signal_quality, _), sim_state) = tokio::try_join!(
+        modem.get_manufacturer(),
+        modem.get_model(),
+        modem.get_state(),
+        modem.get_access_technologies(),
+        modem.get_signal_quality(),
+        modem.get_sim_state(),
+    )?;
+    // CDMA modems and modems without SIM have no 3GPP interface
+    let (operator, operator_code, roaming) = match tokio::try_join!(
+        modem.get_operator_name(),
+        modem.get_operator_code(),
+        modem.get_registration_state(),
+    ) {
+        Ok((operator, operator_code, registration)) => (
+            Some(operator),
+            Some(operator_code),
+            registration.is_roaming(),
+        ),
+        Err(_) => (None, None, false),
+    };
+    let access_technologies = access_technologies(access_technologies_flags);
+
+    Ok(json!({
+        "path": modem.get_path(),
+        "manufacturer": manufacturer,
+        "model": model,
+        "state": state.to_string(),
+        "operator": operator,
+        "operatorCode": operator_code,
+        // The fastest technology in use
+        "accessTechnology": access_technologies.last(),
+        "accessTechnologies": access_technologies,
+        "signalQuality": signal_quality,
+        "simState": sim_state,
+        "roaming": roaming,
+    }))
+}
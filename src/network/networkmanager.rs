@@ -1,12 +1,15 @@
 use super::{
     active_connection::ActiveConnection,
-    device::{self, Device},
+    device::{self, Device, NMDeviceType, WirelessDevice},
+    settings::NewConnectionSettings,
 };
-use std::{error::Error, fmt::Display};
+use crate::error::Error;
+use std::fmt::Display;
+
 use zbus::dbus_proxy;
 use zbus::Connection;
 use zbus::{self, PropertyStream};
-use zvariant::{OwnedObjectPath, OwnedValue};
+use zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
 
 #[dbus_proxy(
     interface = "org.freedesktop.NetworkManager",
@@ -15,6 +18,24 @@ use zvariant::{OwnedObjectPath, OwnedValue};
 pub trait NetworkManager {
     fn get_devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
     fn enable(&self, state: bool) -> zbus::Result<()>;
+    fn check_connectivity(&self) -> zbus::Result<u32>;
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<OwnedObjectPath>;
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> zbus::Result<()>;
+    fn add_and_activate_connection(
+        &self,
+        connection: NewConnectionSettings<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<(OwnedObjectPath, OwnedObjectPath)>;
+    #[dbus_proxy(signal)]
+    fn device_added(&self, device_path: ObjectPath<'_>) -> zbus::Result<()>;
+    #[dbus_proxy(signal)]
+    fn device_removed(&self, device_path: ObjectPath<'_>) -> zbus::Result<()>;
     #[dbus_proxy(property)]
     fn set_wireless_enabled(&self, state: bool) -> zbus::Result<()>;
     #[dbus_proxy(property)]
@@ -22,6 +43,14 @@ pub trait NetworkManager {
     #[dbus_proxy(property)]
     fn wireless_hardware_enabled(&self) -> zbus::Result<bool>;
     #[dbus_proxy(property)]
+    fn set_wwan_enabled(&self, state: bool) -> zbus::Result<()>;
+    #[dbus_proxy(property)]
+    fn wwan_enabled(&self) -> zbus::Result<bool>;
+    #[dbus_proxy(property)]
+    fn wwan_hardware_enabled(&self) -> zbus::Result<bool>;
+    #[dbus_proxy(property)]
+    fn networking_enabled(&self) -> zbus::Result<bool>;
+    #[dbus_proxy(property)]
     fn state(&self) -> zbus::Result<NMState>;
     #[dbus_proxy(property)]
     fn primary_connection_type(&self) -> zbus::Result<String>;
@@ -29,6 +58,32 @@ pub trait NetworkManager {
     fn primary_connection(&self) -> zbus::Result<OwnedObjectPath>;
     #[dbus_proxy(property)]
     fn active_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+    #[dbus_proxy(property)]
+    fn connectivity(&self) -> zbus::Result<NMConnectivityState>;
+    #[dbus_proxy(property)]
+    fn connectivity_check_uri(&self) -> zbus::Result<String>;
+}
+
+#[derive(Debug, OwnedValue, Clone, Copy, PartialEq, Eq)]
+pub enum NMConnectivityState {
+    /// Network connectivity is unknown. This means the connectivity checks are disabled
+    /// (e.g. on server installations) or has not run yet.
+    Unknown = 0,
+    /// The host is not connected to any network.
+    None = 1,
+    /// The Internet connection is hijacked by a captive portal gateway.
+    Portal = 2,
+    /// The host is connected to a network, does not appear to be able to reach the full Internet,
+    /// but a captive portal has not been detected.
+    Limited = 3,
+    /// The host is connected to a network, and appears to be able to reach the full Internet.
+    Full = 4,
+}
+
+impl Display for NMConnectivityState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
 }
 
 #[derive(Debug, OwnedValue, Clone, Copy, PartialEq, Eq)]
@@ -55,67 +110,147 @@ pub struct NetworkManager<'a> {
 }
 
 impl NetworkManager<'_> {
-    pub async fn new(connection: &Connection) -> Result<NetworkManager<'_>, Box<dyn Error>> {
+    pub async fn new(connection: &Connection) -> Result<NetworkManager<'_>, Error> {
         Ok(NetworkManager {
-            proxy: NetworkManagerProxy::new(&connection).await?,
-            connection: &connection,
+            proxy: NetworkManagerProxy::new(connection).await?,
+            connection,
         })
     }
-    pub async fn get_devices(&self) -> Result<Vec<device::Device>, Box<dyn Error>> {
+    pub async fn get_devices(&self) -> Result<Vec<device::Device<'_>>, Error> {
+        let devices_path = self.proxy.get_devices().await?;
+        let mut devices = vec![];
+        for device_path in devices_path {
+            devices.push(Device::new(device_path, self.connection).await?)
+        }
+        Ok(devices)
+    }
+    pub async fn get_wireless_devices(&self) -> Result<Vec<WirelessDevice<'_>>, Error> {
         let devices_path = self.proxy.get_devices().await?;
         let mut devices = vec![];
         for device_path in devices_path {
-            devices.push(Device::new(device_path, &self.connection).await?)
+            if device::get_device_type(&device_path, self.connection).await? == NMDeviceType::Wifi {
+                devices.push(WirelessDevice::new(device_path, self.connection).await?)
+            }
         }
         Ok(devices)
     }
-    pub async fn set_enable(&self, state: bool) -> Result<(), Box<dyn Error>> {
+    pub async fn set_enable(&self, state: bool) -> Result<(), Error> {
         self.proxy.enable(state).await?;
         Ok(())
     }
-    pub async fn set_wireless_enabled(&self, state: bool) -> Result<(), Box<dyn Error>> {
+    pub async fn set_wireless_enabled(&self, state: bool) -> Result<(), Error> {
         self.proxy.set_wireless_enabled(state).await?;
         Ok(())
     }
-    pub async fn is_wireless_enable(&self) -> Result<bool, Box<dyn Error>> {
+    pub async fn is_wireless_enable(&self) -> Result<bool, Error> {
         Ok(self.proxy.wireless_enabled().await?)
     }
-    pub async fn is_wireless_hardware_enable(&self) -> Result<bool, Box<dyn Error>> {
+    pub async fn is_wireless_hardware_enable(&self) -> Result<bool, Error> {
         Ok(self.proxy.wireless_hardware_enabled().await?)
     }
-    pub async fn get_state(&self) -> Result<NMState, Box<dyn Error>> {
+    pub async fn set_wwan_enabled(&self, state: bool) -> Result<(), Error> {
+        self.proxy.set_wwan_enabled(state).await?;
+        Ok(())
+    }
+    pub async fn is_wwan_enable(&self) -> Result<bool, Error> {
+        Ok(self.proxy.wwan_enabled().await?)
+    }
+    pub async fn is_wwan_hardware_enable(&self) -> Result<bool, Error> {
+        Ok(self.proxy.wwan_hardware_enabled().await?)
+    }
+    pub async fn is_networking_enable(&self) -> Result<bool, Error> {
+        Ok(self.proxy.networking_enabled().await?)
+    }
+    pub async fn get_state(&self) -> Result<NMState, Error> {
         Ok(self.proxy.state().await?)
     }
-    pub async fn get_primary_connection_type(&self) -> Result<String, Box<dyn Error>> {
+    pub async fn get_connectivity(&self) -> Result<NMConnectivityState, Error> {
+        Ok(self.proxy.connectivity().await?)
+    }
+    /// URI probed by NetworkManager to check the connectivity, this is not the portal login page
+    /// but the portal usually intercepts it when opened in a browser
+    pub async fn get_connectivity_check_uri(&self) -> Result<String, Error> {
+        Ok(self.proxy.connectivity_check_uri().await?)
+    }
+    /// Whether NetworkManager sees a full internet access, asking it to check again when `recheck`
+    pub async fn check_connectivity(&self, recheck: bool) -> Result<bool, Error> {
+        let connectivity = match recheck {
+            true => OwnedValue::from(self.proxy.check_connectivity().await?).try_into()?,
+            false => self.get_connectivity().await?,
+        };
+        Ok(connectivity == NMConnectivityState::Full)
+    }
+    pub async fn get_primary_connection_type(&self) -> Result<String, Error> {
         Ok(self.proxy.primary_connection_type().await?)
     }
-    pub async fn get_primary_connection(&self) -> Result<ActiveConnection, Box<dyn Error>> {
+    pub async fn get_primary_connection(&self) -> Result<ActiveConnection<'_>, Error> {
         let connection_path = self.proxy.primary_connection().await?;
-        Ok(ActiveConnection::new(connection_path, &self.connection).await?)
+        ActiveConnection::new(connection_path, self.connection).await
     }
-    pub async fn get_active_connections(&self) -> Result<Vec<ActiveConnection>, Box<dyn Error>> {
-        let connections_path = self
+    /// Activate the saved profile at `connection_path` on `device_path`.
+    /// `specific_object` can be used to select an access point, "/" let NetworkManager choose.
+    pub async fn activate_connection(
+        &self,
+        connection_path: &str,
+        device_path: &str,
+        specific_object: &str,
+    ) -> Result<ActiveConnection<'_>, Error> {
+        let active_path = self
+            .proxy
+            .activate_connection(
+                &ObjectPath::try_from(connection_path)?,
+                &ObjectPath::try_from(device_path)?,
+                &ObjectPath::try_from(specific_object)?,
+            )
+            .await?;
+        ActiveConnection::new(active_path, self.connection).await
+    }
+    /// Save a new profile built from `settings` and activate it on `device_path`.
+    pub async fn add_and_activate_connection(
+        &self,
+        settings: NewConnectionSettings<'_>,
+        device_path: &str,
+        specific_object: &str,
+    ) -> Result<ActiveConnection<'_>, Error> {
+        let (_, active_path) = self
             .proxy
-            .get_property::<Vec<OwnedObjectPath>>("Devices")
+            .add_and_activate_connection(
+                settings,
+                &ObjectPath::try_from(device_path)?,
+                &ObjectPath::try_from(specific_object)?,
+            )
             .await?;
+        ActiveConnection::new(active_path, self.connection).await
+    }
+    pub async fn deactivate_connection(
+        &self,
+        active_connection: &ActiveConnection<'_>,
+    ) -> Result<(), Error> {
+        self.proxy
+            .deactivate_connection(&ObjectPath::try_from(active_connection.get_path().as_str())?)
+            .await?;
+        Ok(())
+    }
+    pub async fn get_active_connections(&self) -> Result<Vec<ActiveConnection<'_>>, Error> {
+        let connections_path = self.proxy.active_connections().await?;
         let mut connections = vec![];
         for connection_path in connections_path {
-            connections.push(ActiveConnection::new(connection_path, &self.connection).await?)
+            connections.push(ActiveConnection::new(connection_path, self.connection).await?)
         }
         Ok(connections)
     }
-    // pub async fn get_icon_path(&self, signal_strength: u8) -> Result<String, Box<dyn Error>> {
+    // pub async fn get_icon_path(&self, signal_strength: u8) -> Result<String, Error> {
     //     let icon = NetworkState::new(
     //         self.get_state().await?,
     //         signal_strength,
-    //         is_connected_to_internet().await,
+    //         probe(&ProbeConfig::default(), self).await.connected,
     //     );
     //     Ok(icon_path() )
 
     // }
     pub async fn receive_property_changed(
         &self,
-    ) -> Result<PropertyStream<NMState>, Box<dyn Error>> {
+    ) -> Result<PropertyStream<'_, NMState>, Error> {
         // let receive_state_changed = self.proxy.receive_state_changed();
         // let mut b = receive_state_changed.boxed();
         // let c = b.next().await.ok_or("no prp")?;
@@ -123,8 +258,13 @@ impl NetworkManager<'_> {
         todo!()
     }
 }
-pub fn icon_path(state: NMState, global_conn: bool, signal_strength: u8) -> String {
-    NetworkState::new(state, signal_strength, global_conn).path()
+pub fn icon_path(
+    state: NMState,
+    global_conn: bool,
+    captive_portal: bool,
+    signal_strength: u8,
+) -> String {
+    NetworkState::new(state, signal_strength, global_conn, captive_portal).path()
 }
 
 pub enum NetworkState {
@@ -135,17 +275,25 @@ pub enum NetworkState {
     Disconnecting,
     Connected(u8),
     ConnectedGlobal(u8),
+    /// Connected but a login page hijack the traffic
+    CaptivePortal,
 }
 impl NetworkState {
-    pub fn new(state: NMState, signal_strength: u8, is_connected: bool) -> NetworkState {
+    pub fn new(
+        state: NMState,
+        signal_strength: u8,
+        is_connected: bool,
+        captive_portal: bool,
+    ) -> NetworkState {
         match state {
             NMState::Unknow | NMState::Asleep => NetworkState::Alseep,
             NMState::Connecting => NetworkState::Connecting,
             NMState::Disconnecting => NetworkState::Disconnecting,
             NMState::ConnectedGlobal | NMState::ConnectedSite | NMState::ConnectedLocal => {
-                match is_connected {
-                    true => NetworkState::ConnectedGlobal(signal_strength),
-                    false => NetworkState::Connected(signal_strength),
+                match (captive_portal, is_connected) {
+                    (true, _) => NetworkState::CaptivePortal,
+                    (false, true) => NetworkState::ConnectedGlobal(signal_strength),
+                    (false, false) => NetworkState::Connected(signal_strength),
                 }
             }
             NMState::Disconnected => NetworkState::Disconnected,
@@ -157,19 +305,20 @@ impl NetworkState {
             NetworkState::Disconnected => "/image/Disconnected.svg",
             NetworkState::Connecting => "/image/Connecting.gif",
             NetworkState::Disconnecting => "/image/Disconnecting.svg",
+            NetworkState::CaptivePortal => "/image/CaptivePortal.svg",
             NetworkState::Connected(x) => match x {
                 x if *x < 25 => "/image/Connected-1.svg",
                 x if 25 <= *x && *x < 50 => "/image/Connected-2.svg",
                 x if 50 <= *x && *x < 75 => "/image/Connected-3.svg",
-                x if 75 <= *x && *x <= 100 => "/image/Connected-4.svg",
-                _ => panic!("Can't have a signal strengh over 100"),
+                // Strength is a percentage, anything above is full signal
+                _ => "/image/Connected-4.svg",
             },
             NetworkState::ConnectedGlobal(x) => match x {
                 x if *x < 25 => "/image/ConnectedGlobal-1.svg",
                 x if 25 <= *x && *x < 50 => "/image/ConnectedGlobal-2.svg",
                 x if 50 <= *x && *x < 75 => "/image/ConnectedGlobal-3.svg",
-                x if 75 <= *x && *x <= 100 => "/image/ConnectedGlobal-4.svg",
-                _ => panic!("Can't have a signal strengh over 100"),
+                // Strength is a percentage, anything above is full signal
+                _ => "/image/ConnectedGlobal-4.svg",
             },
         }
         .to_string()
@@ -0,0 +1,217 @@
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use zbus::Connection;
+
+use super::{
+    access_point::ssid_to_string, active_connection::ActiveConnection, device::Device,
+    networkmanager::NetworkManager, receive_properties_changed, watch_service,
+};
+use crate::error::{report, Error};
+
+/// Output a json whenever an active connection, one of its devices or its addresses change.
+/// Every active connection is listed with all its devices and addresses, the primary one first.
+/// No connection is output while NetworkManager is not running.
+///
+/// ``` json
+/// {
+///   "primary": "Wired connection 1",
+///   "connections": [
+///     {
+///       "id": "Wired connection 1",
+///       "uuid": "1b0e5e55-6a1f-4d2b-9a61-2b6e2b8f6f1e",
+///       "type": "802-3-ethernet",
+///       "state": "Activated",
+///       "primary": true,
+///       "vpn": false,
+///       "default": true,
+///       "default6": false,
+///       "devices": [
+///         {"interface": "enp0s31f6", "kind": "wired", "state": "Activated", "hwAddress": "AA:BB:CC:DD:EE:FF", "ssid": null}
+///       ],
+///       "ip4": {"gateway": "192.168.1.1", "addresses": ["192.168.1.20/24"]},
+///       "ip6": {"gateway": "", "addresses": ["fe80::1/64"]}
+///     }
+///   ]
+/// }
+/// ```
+pub(crate) async fn listener() -> Result<(), Error> {
+    let connection = Connection::system().await?;
+    watch_service(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        json!({ "primary": null, "connections": [] }),
+        || listen(&connection),
+    )
+    .await
+}
+
+async fn listen(connection: &Connection) -> Result<(), Error> {
+    let nm = NetworkManager::new(connection).await?;
+
+    let mut events = futures_util::stream::select_all(vec![
+        nm.proxy
+            .receive_active_connections_changed()
+            .await
+            .map(|_| ())
+            .boxed(),
+        nm.proxy
+            .receive_primary_connection_changed()
+            .await
+            .map(|_| ())
+            .boxed(),
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/ActiveConnection",
+            "org.freedesktop.NetworkManager.Connection.Active",
+            &[
+                "State",
+                "Devices",
+                "Default",
+                "Default6",
+                "Ip4Config",
+                "Ip6Config",
+            ],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/Devices",
+            "org.freedesktop.NetworkManager.Device",
+            &["State"],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/IP4Config",
+            "org.freedesktop.NetworkManager.IP4Config",
+            &[],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/IP6Config",
+            "org.freedesktop.NetworkManager.IP6Config",
+            &[],
+        )
+        .await?,
+    ]);
+
+    report(print_overview(&nm).await)?;
+    while events.next().await.is_some() {
+        report(print_overview(&nm).await)?;
+    }
+
+    Ok(())
+}
+
+async fn print_overview(nm: &NetworkManager<'_>) -> Result<(), Error> {
+    // "/" when there is no primary connection
+    let primary_path = nm.get_primary_connection().await?.get_path().clone();
+    let mut primary = None;
+    let mut connections = vec![];
+    for active_connection in nm.get_active_connections().await? {
+        let is_primary = *active_connection.get_path() == primary_path;
+        // Connections can vanish between the listing and the property reads
+        if let Ok(data) = connection_data(&active_connection, is_primary).await {
+            if is_primary {
+                primary = Some(data["id"].clone());
+            }
+            connections.push(data);
+        }
+    }
+    connections.sort_by_key(|connection| connection["primary"] != true);
+    println!(
+        "{}",
+        json!({
+            "primary": primary,
+            "connections": connections,
+        })
+    );
+    Ok(())
+}
+
+async fn connection_data(
+    active_connection: &ActiveConnection<'_>,
+    is_primary: bool,
+) -> Result<Value, Error> {
+    let (id, uuid, kind, state, vpn, default, default6) = tokio::try_join!(
+        active_connection.get_id(),
+        active_connection.get_uuid(),
+        active_connection.get_type(),
+        active_connection.get_state(),
+        active_connection.is_vpn(),
+        active_connection.is_default(),
+        active_connection.is_default6(),
+    )?;
+
+    let mut devices = vec![];
+    for device in active_connection.get_devices().await? {
+        devices.push(device_data(&device).await?);
+    }
+
+    // No IP configuration until the connection is activated
+    let ip4 = match active_connection.get_ip4_config().await {
+        Ok(ip4_config) => {
+            match tokio::try_join!(ip4_config.get_gateway(), ip4_config.get_addresses()) {
+                Ok((gateway, addresses)) => json!({
+                    "gateway": gateway,
+                    "addresses": addresses
+                        .iter()
+                        .map(|address| format!("{}/{}", address.address, address.mask_cird))
+                        .collect::<Vec<_>>(),
+                }),
+                Err(_) => Value::Null,
+            }
+        }
+        Err(_) => Value::Null,
+    };
+    let ip6 = match active_connection.get_ip6_config().await {
+        Ok(ip6_config) => {
+            match tokio::try_join!(ip6_config.get_gateway(), ip6_config.get_addresses()) {
+                Ok((gateway, addresses)) => json!({
+                    "gateway": gateway,
+                    "addresses": addresses
+                        .iter()
+                        .map(|address| format!("{}/{}", address.address, address.mask_cird))
+                        .collect::<Vec<_>>(),
+                }),
+                Err(_) => Value::Null,
+            }
+        }
+        Err(_) => Value::Null,
+    };
+
+    Ok(json!({
+        "id": id,
+        "uuid": uuid,
+        "type": kind,
+        "state": state.to_string(),
+        "primary": is_primary,
+        "vpn": vpn,
+        "default": default,
+        "default6": default6,
+        "devices": devices,
+        "ip4": ip4,
+        "ip6": ip6,
+    }))
+}
+
+async fn device_data(device: &Device<'_>) -> Result<Value, Error> {
+    let (interface, state, hw_address) = tokio::try_join!(
+        device.get_interface(),
+        device.get_state(),
+        device.get_hw_address(),
+    )?;
+    let (kind, _, _, ssid) = device.get_device_data().await?;
+    let ssid = match device {
+        Device::WirelessDevice(_) => Some(ssid_to_string(&ssid)),
+        _ => None,
+    };
+    Ok(json!({
+        "interface": interface,
+        "kind": kind,
+        "state": format!("{:?}", state),
+        "hwAddress": hw_address,
+        "ssid": ssid,
+    }))
+}
@@ -1,24 +1,56 @@
-use std::time::Duration;
+use std::{net::IpAddr, time::Duration};
 
-use surge_ping::{Config, Client, PingIdentifier, PingSequence};
+use surge_ping::{Client, Config, PingIdentifier, PingSequence, Pinger, SurgeError, ICMP};
 
-pub async fn ping() -> Result<(surge_ping::IcmpPacket, Duration), surge_ping::SurgeError> {
-    let ip = tokio::net::lookup_host(format!("google.fr:0"))
-    .await?
-    .next()
-    .map(|val| val.ip()).unwrap();
+/// Send one ICMP echo request to `host` (a name or an IP address)
+pub async fn ping(host: &str) -> Result<(surge_ping::IcmpPacket, Duration), SurgeError> {
+    let mut pinger = IcmpPinger::new(host).await?;
+    pinger.ping().await
+}
 
-    let config = Config::new();
+async fn lookup_host(host: &str) -> Result<IpAddr, SurgeError> {
+    let ip = tokio::net::lookup_host(format!("{}:0", host))
+        .await?
+        .next()
+        .map(|val| val.ip())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "host has no address"))?;
+    Ok(ip)
+}
 
-    let payload = vec![0; 56];
-    let client = Client::new(&config).unwrap();
-    let mut pinger = client.pinger(ip, PingIdentifier(111)).await;
-    pinger.ping(PingSequence(0), &payload).await
+/// Send echo requests to the same host with increasing sequence numbers
+pub struct IcmpPinger {
+    /// The client own the task receiving the replies, it must live as long as the pinger
+    _client: Client,
+    pinger: Pinger,
+    sequence: u16,
 }
 
-pub async fn is_connected_to_internet() -> bool {
-    match ping().await {
-        Ok(_) => true,
-        Err(_) => false,
+impl IcmpPinger {
+    pub async fn new(host: &str) -> Result<IcmpPinger, SurgeError> {
+        let ip = lookup_host(host).await?;
+        let config = match ip {
+            IpAddr::V4(_) => Config::new(),
+            IpAddr::V6(_) => Config::builder().kind(ICMP::V6).build(),
+        };
+        let client = Client::new(&config)?;
+        let pinger = client.pinger(ip, PingIdentifier(111)).await;
+        Ok(IcmpPinger {
+            _client: client,
+            pinger,
+            sequence: 0,
+        })
+    }
+
+    /// Time to wait for a reply before considering the packet lost (default: 2s)
+    pub fn timeout(&mut self, timeout: Duration) -> &mut IcmpPinger {
+        self.pinger.timeout(timeout);
+        self
     }
-}
\ No newline at end of file
+
+    pub async fn ping(&mut self) -> Result<(surge_ping::IcmpPacket, Duration), SurgeError> {
+        let payload = vec![0; 56];
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+        self.pinger.ping(PingSequence(sequence), &payload).await
+    }
+}
@@ -0,0 +1,120 @@
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use zbus::{Connection, MatchRule, MessageStream};
+
+use super::{
+    settings::{Settings, SettingsConnection},
+    watch_service,
+};
+use crate::error::{report, Error};
+
+/// Output a json array of the saved connection profiles whenever one is added, removed or updated,
+/// most recently used first
+/// `[]` is output while NetworkManager is not running.
+///
+/// ``` json
+/// [
+///   {
+///     "id": "home",
+///     "uuid": "1b0e5e55-6a1f-4d2b-9a61-2b6e2b8f6f1e",
+///     "type": "802-11-wireless",
+///     "autoconnect": true,
+///     "timestamp": 1697530000
+///   }
+/// ]
+/// ```
+pub(crate) async fn listener() -> Result<(), Error> {
+    let connection = Connection::system().await?;
+    watch_service(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        json!([]),
+        || listen(&connection),
+    )
+    .await
+}
+
+async fn listen(connection: &Connection) -> Result<(), Error> {
+    let settings = Settings::new(connection).await?;
+
+    let rule = MatchRule::builder()
+        .msg_type(zbus::MessageType::Signal)
+        .interface("org.freedesktop.NetworkManager.Settings.Connection")?
+        .member("Updated")?
+        .path_namespace("/org/freedesktop/NetworkManager/Settings")?
+        .build();
+    let mut events = futures_util::stream::select_all(vec![
+        settings.receive_new_connection().await?.map(|_| ()).boxed(),
+        settings
+            .receive_connection_removed()
+            .await?
+            .map(|_| ())
+            .boxed(),
+        MessageStream::for_match_rule(rule, connection, None)
+            .await?
+            .map(|_| ())
+            .boxed(),
+    ]);
+
+    report(print_profiles(&settings).await)?;
+    while events.next().await.is_some() {
+        report(print_profiles(&settings).await)?;
+    }
+
+    Ok(())
+}
+
+async fn print_profiles(settings: &Settings<'_>) -> Result<(), Error> {
+    let mut profiles = vec![];
+    for profile in settings.list_connections().await? {
+        // Profiles can vanish between the listing and the settings read
+        if let Ok(profile) = profile_data(&profile).await {
+            profiles.push(profile);
+        }
+    }
+    profiles.sort_by_key(|profile| std::cmp::Reverse(profile["timestamp"].as_u64()));
+    println!("{}", Value::Array(profiles));
+    Ok(())
+}
+
+async fn profile_data(profile: &SettingsConnection<'_>) -> Result<Value, Error> {
+    let settings = profile.get_profile_settings().await?;
+    Ok(json!({
+        "id": settings.get_id()?,
+        "uuid": settings.get_uuid()?,
+        "type": settings.get_type()?,
+        "autoconnect": settings.is_autoconnect()?,
+        "timestamp": settings.get_timestamp()?, // Seconds since the Unix Epoch, 0 when never used
+    }))
+}
+
+async fn get_profile<'a>(
+    settings: &'a Settings<'_>,
+    id: &str,
+) -> Result<SettingsConnection<'a>, Error> {
+    Ok(settings
+        .get_connection_by_id(id)
+        .await?
+        .ok_or_else(|| format!("no saved connection named \"{}\"", id))?)
+}
+
+/// Delete the saved profile `id`
+pub(crate) async fn forget(id: &str) -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let settings = Settings::new(&connection).await?;
+    get_profile(&settings, id).await?.delete().await?;
+    println!("{}", json!({ "id": id, "forgotten": true }));
+    Ok(())
+}
+
+/// Allow or forbid NetworkManager to activate the saved profile `id` by itself
+pub(crate) async fn autoconnect(id: &str, autoconnect: bool) -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let settings = Settings::new(&connection).await?;
+    let profile = get_profile(&settings, id).await?;
+    profile.set_autoconnect(autoconnect).await?;
+    println!("{}", profile_data(&profile).await?);
+    Ok(())
+}
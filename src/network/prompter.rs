@@ -0,0 +1,360 @@
+use std::{
+    collections::HashMap,
+    io,
+    os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{broadcast, oneshot},
+};
+
+/// Secrets NetworkManager needs to activate a connection
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretRequest {
+    /// Name of the connection profile
+    pub connection: String,
+    /// Type of the connection profile ("802-11-wireless", "vpn", ...)
+    pub connection_type: String,
+    /// Setting holding the secrets ("802-11-wireless-security", "802-1x", "vpn", ...)
+    pub setting: String,
+    /// Name of every secret to ask ("psk", "password", ...)
+    pub keys: Vec<String>,
+    /// Message to show, given by VPN plugins
+    pub message: Option<String>,
+    /// The previous secrets were wrong
+    pub retry: bool,
+}
+
+/// Ask the user for the secrets of a [`SecretRequest`]
+#[allow(clippy::double_must_use)]
+#[async_trait]
+pub trait Prompter: Send + Sync {
+    /// Value of every key of `request`, `None` when the user canceled
+    async fn prompt(&self, request: &SecretRequest) -> io::Result<Option<HashMap<String, String>>>;
+}
+
+/// Ask on the terminal the agent is running in, without echoing what is typed
+pub struct StdinPrompter;
+
+#[async_trait]
+impl Prompter for StdinPrompter {
+    async fn prompt(&self, request: &SecretRequest) -> io::Result<Option<HashMap<String, String>>> {
+        let mut secrets = HashMap::new();
+        if let Some(message) = &request.message {
+            eprintln!("{}", message);
+        }
+        for key in &request.keys {
+            let prompt = format!("{} for \"{}\": ", key, request.connection);
+            let value = tokio::task::spawn_blocking(move || rpassword::prompt_password(prompt))
+                .await
+                .map_err(io::Error::other)?;
+            match value {
+                Ok(value) => secrets.insert(key.clone(), value),
+                // End of input is a cancellation
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(err) => return Err(err),
+            };
+        }
+        Ok(Some(secrets))
+    }
+}
+
+/// Run a command once for every key and read the secret on its standard output,
+/// like `rofi -dmenu -password`. A non-zero exit status is a cancellation.
+///
+/// The request is described to the command with the environment variables
+/// `NM_SECRET_CONNECTION`, `NM_SECRET_SETTING`, `NM_SECRET_KEY` and `NM_SECRET_MESSAGE`.
+pub struct CommandPrompter {
+    pub command: String,
+}
+
+#[async_trait]
+impl Prompter for CommandPrompter {
+    async fn prompt(&self, request: &SecretRequest) -> io::Result<Option<HashMap<String, String>>> {
+        let mut secrets = HashMap::new();
+        for key in &request.keys {
+            let output = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(&self.command)
+                .env("NM_SECRET_CONNECTION", &request.connection)
+                .env("NM_SECRET_SETTING", &request.setting)
+                .env("NM_SECRET_KEY", key)
+                .env(
+                    "NM_SECRET_MESSAGE",
+                    request.message.as_deref().unwrap_or_default(),
+                )
+                .stderr(std::process::Stdio::inherit())
+                .output()
+                .await?;
+            if !output.status.success() {
+                return Ok(None);
+            }
+            let value = String::from_utf8_lossy(&output.stdout);
+            secrets.insert(key.clone(), value.trim_end_matches('\n').to_string());
+        }
+        Ok(Some(secrets))
+    }
+}
+
+/// Answer of a client of the [`SocketPrompter`]
+#[derive(Debug, Deserialize)]
+struct SocketResponse {
+    id: u64,
+    #[serde(default)]
+    secrets: Option<HashMap<String, String>>,
+}
+
+struct PendingRequest {
+    /// Json line sent to the clients
+    line: String,
+    answer: oneshot::Sender<Option<HashMap<String, String>>>,
+}
+
+/// Serve the requests as json lines on a Unix socket.
+/// Every client receive the pending requests then each new one:
+///
+/// ``` json
+/// {"id": 1, "connection": "home", "connectionType": "802-11-wireless", "setting": "802-11-wireless-security", "keys": ["psk"], "message": null, "retry": false}
+/// ```
+///
+/// Any client can answer with the secrets, or without them to cancel,
+/// and every client is then told the request is done, as when NetworkManager cancels it:
+///
+/// ``` json
+/// {"id": 1, "secrets": {"psk": "hunter22"}}
+/// {"id": 1, "done": true}
+/// ```
+pub struct SocketPrompter {
+    requests: broadcast::Sender<String>,
+    pending: Arc<Mutex<HashMap<u64, PendingRequest>>>,
+    next_id: AtomicU64,
+}
+
+impl SocketPrompter {
+    /// Listen on `path`, only usable by the current user, replacing any stale socket
+    /// of the current user
+    pub async fn bind(path: &Path) -> io::Result<SocketPrompter> {
+        match tokio::fs::symlink_metadata(path).await {
+            Ok(metadata) => {
+                // SAFETY: getuid can't fail
+                let uid = unsafe { libc::getuid() };
+                if !metadata.file_type().is_socket() || metadata.uid() != uid {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!(
+                            "{} exists and is not a socket of the current user",
+                            path.display()
+                        ),
+                    ));
+                }
+            }
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            Err(_) => (),
+        }
+        // Bound in a directory only the user can enter then moved in place, so that nobody
+        // else can connect before the socket is made private
+        let private_dir = path.with_extension(format!("{}.tmp", std::process::id()));
+        tokio::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&private_dir)
+            .await?;
+        let bound = async {
+            let private_path = private_dir.join("socket");
+            let listener = UnixListener::bind(&private_path)?;
+            tokio::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(0o600))
+                .await?;
+            tokio::fs::rename(&private_path, path).await?;
+            Ok::<_, io::Error>(listener)
+        }
+        .await;
+        let _ = tokio::fs::remove_dir_all(&private_dir).await;
+        let listener = bound?;
+        let (requests, _) = broadcast::channel(16);
+        let pending: Arc<Mutex<HashMap<u64, PendingRequest>>> = Default::default();
+
+        let accept_requests = requests.clone();
+        let accept_pending = pending.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_client(
+                    stream,
+                    accept_pending.clone(),
+                    accept_requests.clone(),
+                ));
+            }
+        });
+
+        Ok(SocketPrompter {
+            requests,
+            pending,
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    /// `$XDG_RUNTIME_DIR/eww-network-secrets.sock`, an error when `XDG_RUNTIME_DIR` isn't set
+    /// as there is no other directory private to the user
+    pub fn default_path() -> io::Result<PathBuf> {
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "XDG_RUNTIME_DIR is not set, give the socket path with --socket",
+            )
+        })?;
+        Ok(PathBuf::from(runtime_dir).join("eww-network-secrets.sock"))
+    }
+}
+
+async fn serve_client(
+    stream: UnixStream,
+    pending: Arc<Mutex<HashMap<u64, PendingRequest>>>,
+    notify: broadcast::Sender<String>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    // Requests sent before the client connected, the requests are sent with the lock held
+    // so that none is both in the backlog and received
+    let (backlog, mut requests): (Vec<String>, _) = {
+        let pending = pending.lock().expect("lock poisoned");
+        let backlog = pending
+            .values()
+            .map(|request| request.line.clone())
+            .collect();
+        (backlog, notify.subscribe())
+    };
+    let write_requests = async move {
+        for line in backlog {
+            writer.write_all(format!("{}\n", line).as_bytes()).await?;
+        }
+        while let Ok(line) = requests.recv().await {
+            writer.write_all(format!("{}\n", line).as_bytes()).await?;
+        }
+        Ok::<_, io::Error>(())
+    };
+    let read_responses = async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            let Ok(response) = serde_json::from_str::<SocketResponse>(&line) else {
+                continue;
+            };
+            let request = pending.lock().expect("lock poisoned").remove(&response.id);
+            if let Some(request) = request {
+                let _ = request.answer.send(response.secrets);
+                let _ = notify.send(json!({ "id": response.id, "done": true }).to_string());
+            }
+        }
+        Ok::<_, io::Error>(())
+    };
+    // The client is gone as soon as one side is closed
+    let _ = tokio::try_join!(write_requests, read_responses);
+}
+
+#[async_trait]
+impl Prompter for SocketPrompter {
+    async fn prompt(&self, request: &SecretRequest) -> io::Result<Option<HashMap<String, String>>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut line = serde_json::to_value(request)?;
+        line["id"] = json!(id);
+        let line = line.to_string();
+
+        let (answer, answered) = oneshot::channel();
+        {
+            let mut pending = self.pending.lock().expect("lock poisoned");
+            pending.insert(
+                id,
+                PendingRequest {
+                    line: line.clone(),
+                    answer,
+                },
+            );
+            // Nobody listening yet is fine, the request is replayed to new clients
+            let _ = self.requests.send(line);
+        }
+
+        // Dropped as well when NetworkManager cancels the request and the prompt is aborted
+        let _done = DoneGuard {
+            id,
+            pending: self.pending.clone(),
+            requests: self.requests.clone(),
+        };
+        let secrets = answered.await.unwrap_or(None);
+        Ok(secrets)
+    }
+}
+
+/// Forget a request of the [`SocketPrompter`] not answered by a client,
+/// and tell the clients it is done
+struct DoneGuard {
+    id: u64,
+    pending: Arc<Mutex<HashMap<u64, PendingRequest>>>,
+    requests: broadcast::Sender<String>,
+}
+
+impl Drop for DoneGuard {
+    fn drop(&mut self) {
+        let request = self.pending.lock().expect("lock poisoned").remove(&self.id);
+        if request.is_some() {
+            let _ = self
+                .requests
+                .send(json!({ "id": self.id, "done": true }).to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn request() -> SecretRequest {
+        SecretRequest {
+            connection: "home".to_string(),
+            connection_type: "802-11-wireless".to_string(),
+            setting: "802-11-wireless-security".to_string(),
+            keys: vec!["psk".to_string()],
+            message: None,
+            retry: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn canceled_request_is_done() {
+        let dir = std::env::temp_dir().join(format!("prompter-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("secrets.sock");
+        let prompter = Arc::new(SocketPrompter::bind(&path).await.unwrap());
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let client = UnixStream::connect(&path).await.unwrap();
+        let mut lines = BufReader::new(client).lines();
+        let prompt = tokio::spawn({
+            let prompter = prompter.clone();
+            async move { prompter.prompt(&request()).await }
+        });
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert!(line.contains("\"keys\":[\"psk\"]"), "{}", line);
+
+        // As when NetworkManager cancels the request
+        prompt.abort();
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert_eq!(line, json!({ "id": 1, "done": true }).to_string());
+
+        // Not replayed to the next clients
+        let client = UnixStream::connect(&path).await.unwrap();
+        let mut lines = BufReader::new(client).lines();
+        let replayed = tokio::time::timeout(Duration::from_millis(200), lines.next_line()).await;
+        assert!(replayed.is_err(), "{:?}", replayed);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
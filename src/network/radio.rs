@@ -0,0 +1,145 @@
+use clap::ValueEnum;
+use serde_json::json;
+use zbus::Connection;
+
+use super::{networkmanager::NetworkManager, rfkill};
+use crate::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RadioAction {
+    On,
+    Off,
+    Toggle,
+}
+
+impl RadioAction {
+    fn apply(&self, enabled: bool) -> bool {
+        match self {
+            RadioAction::On => true,
+            RadioAction::Off => false,
+            RadioAction::Toggle => !enabled,
+        }
+    }
+}
+
+/// Whether the Bluetooth radios are on, `None` when there is none or rfkill is not supported
+async fn bluetooth_enabled() -> Option<bool> {
+    let blocked = rfkill::is_bluetooth_blocked().await.ok().flatten()?;
+    Some(!blocked)
+}
+
+/// Every radio is off, a missing Bluetooth radio counting as off
+fn is_airplane(wifi: bool, wwan: bool, bluetooth: Option<bool>) -> bool {
+    !wifi && !wwan && !bluetooth.unwrap_or(false)
+}
+
+/// Output a json with the state of every radio
+///
+/// ``` json
+/// {
+///   "networking": true,
+///   "wifi": true,
+///   "wifiHardware": true,
+///   "wwan": false,
+///   "wwanHardware": true,
+///   "bluetooth": true,
+///   "airplane": false
+/// }
+/// ```
+async fn print_radios(nm: &NetworkManager<'_>) -> Result<(), Error> {
+    let (networking, wifi, wifi_hardware, wwan, wwan_hardware) = tokio::try_join!(
+        nm.is_networking_enable(),
+        nm.is_wireless_enable(),
+        nm.is_wireless_hardware_enable(),
+        nm.is_wwan_enable(),
+        nm.is_wwan_hardware_enable(),
+    )?;
+    let bluetooth = bluetooth_enabled().await;
+    println!(
+        "{}",
+        json!({
+            "networking": networking,
+            "wifi": wifi,
+            "wifiHardware": wifi_hardware,
+            "wwan": wwan,
+            "wwanHardware": wwan_hardware,
+            "bluetooth": bluetooth.unwrap_or(false), // false without Bluetooth radio
+            "airplane": is_airplane(wifi, wwan, bluetooth),
+        })
+    );
+    Ok(())
+}
+
+/// Turn the Wi-Fi radio on or off
+pub(crate) async fn wifi(action: RadioAction) -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let nm = NetworkManager::new(&connection).await?;
+    let enabled = nm.is_wireless_enable().await?;
+    nm.set_wireless_enabled(action.apply(enabled)).await?;
+    print_radios(&nm).await
+}
+
+/// Turn the whole NetworkManager networking on or off
+pub(crate) async fn networking(action: RadioAction) -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let nm = NetworkManager::new(&connection).await?;
+    let enabled = nm.is_networking_enable().await?;
+    nm.set_enable(action.apply(enabled)).await?;
+    print_radios(&nm).await
+}
+
+/// Toggle the airplane mode: Wi-Fi, WWAN and Bluetooth all off, or all back on
+pub(crate) async fn airplane() -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let nm = NetworkManager::new(&connection).await?;
+    let (wifi, wwan) = tokio::try_join!(nm.is_wireless_enable(), nm.is_wwan_enable())?;
+    let bluetooth = bluetooth_enabled().await;
+    let radios_on = is_airplane(wifi, wwan, bluetooth);
+
+    tokio::try_join!(
+        nm.set_wireless_enabled(radios_on),
+        nm.set_wwan_enabled(radios_on),
+    )?;
+    // Without Bluetooth radio there is nothing to block
+    let blocked = match bluetooth {
+        Some(_) => rfkill::set_bluetooth_blocked(!radios_on).await,
+        None => Ok(()),
+    };
+    if let Err(error) = blocked {
+        // Without the right to use /dev/rfkill, put the NetworkManager radios back as they were
+        // instead of leaving a half airplane mode
+        tokio::try_join!(nm.set_wireless_enabled(wifi), nm.set_wwan_enabled(wwan))?;
+        return Err(error.into());
+    }
+    print_radios(&nm).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn airplane() {
+        let cases = [
+            (false, false, None, true),
+            (false, false, Some(false), true),
+            (false, false, Some(true), false),
+            (true, false, None, false),
+            (false, true, None, false),
+            (true, true, Some(true), false),
+        ];
+        for (wifi, wwan, bluetooth, expected) in cases {
+            assert_eq!(
+                is_airplane(wifi, wwan, bluetooth),
+                expected,
+                "wifi: {}, wwan: {}, bluetooth: {:?}",
+                wifi,
+                wwan,
+                bluetooth
+            );
+        }
+    }
+}
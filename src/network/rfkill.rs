@@ -0,0 +1,112 @@
+use std::{io, path::Path};
+
+use tokio::io::AsyncWriteExt;
+
+const RFKILL_TYPE_BLUETOOTH: u8 = 2;
+const RFKILL_OP_CHANGE_ALL: u8 = 3;
+
+/// `struct rfkill_event` from linux/rfkill.h
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct RfkillEvent {
+    idx: u32,
+    kind: u8,
+    op: u8,
+    soft: u8,
+    hard: u8,
+}
+
+// SAFETY: only integers and no padding
+unsafe impl bytemuck::Zeroable for RfkillEvent {}
+unsafe impl bytemuck::Pod for RfkillEvent {}
+
+/// Soft block or unblock every Bluetooth radio, like `rfkill block bluetooth`
+pub async fn set_bluetooth_blocked(blocked: bool) -> io::Result<()> {
+    let event = RfkillEvent {
+        idx: 0,
+        kind: RFKILL_TYPE_BLUETOOTH,
+        op: RFKILL_OP_CHANGE_ALL,
+        soft: blocked as u8,
+        hard: 0,
+    };
+    let mut rfkill = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/rfkill")
+        .await?;
+    rfkill.write_all(bytemuck::bytes_of(&event)).await
+}
+
+/// Whether every Bluetooth radio is blocked, `None` when there is no Bluetooth radio
+pub async fn is_bluetooth_blocked() -> io::Result<Option<bool>> {
+    bluetooth_blocked_in(Path::new("/sys/class/rfkill")).await
+}
+
+/// [`is_bluetooth_blocked`] for the rfkill devices listed in `class_dir`
+async fn bluetooth_blocked_in(class_dir: &Path) -> io::Result<Option<bool>> {
+    let mut radios = tokio::fs::read_dir(class_dir).await?;
+    let mut blocked = None;
+    while let Some(radio) = radios.next_entry().await? {
+        let path = radio.path();
+        if tokio::fs::read_to_string(path.join("type")).await?.trim() != "bluetooth" {
+            continue;
+        }
+        let soft = tokio::fs::read_to_string(path.join("soft")).await?;
+        let hard = tokio::fs::read_to_string(path.join("hard")).await?;
+        if soft.trim() == "0" && hard.trim() == "0" {
+            return Ok(Some(false));
+        }
+        blocked = Some(true);
+    }
+    Ok(blocked)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// `(type, soft, hard)` of an rfkill device
+    type Radio = (&'static str, &'static str, &'static str);
+
+    /// A fake `/sys/class/rfkill` holding `radios`
+    fn class_dir(name: &str, radios: &[Radio]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rfkill-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (index, (kind, soft, hard)) in radios.iter().enumerate() {
+            let radio = dir.join(format!("rfkill{}", index));
+            std::fs::create_dir_all(&radio).unwrap();
+            std::fs::write(radio.join("type"), format!("{}\n", kind)).unwrap();
+            std::fs::write(radio.join("soft"), format!("{}\n", soft)).unwrap();
+            std::fs::write(radio.join("hard"), format!("{}\n", hard)).unwrap();
+        }
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn bluetooth_blocked() {
+        let cases: &[(&str, &[Radio], Option<bool>)] = &[
+            ("none", &[], None),
+            ("wifi-only", &[("wlan", "0", "0")], None),
+            (
+                "unblocked",
+                &[("wlan", "1", "0"), ("bluetooth", "0", "0")],
+                Some(false),
+            ),
+            ("soft", &[("bluetooth", "1", "0")], Some(true)),
+            ("hard", &[("bluetooth", "0", "1")], Some(true)),
+            (
+                "one-on",
+                &[("bluetooth", "1", "0"), ("bluetooth", "0", "0")],
+                Some(false),
+            ),
+        ];
+        for (name, radios, expected) in cases {
+            let dir = class_dir(name, radios);
+            let blocked = bluetooth_blocked_in(&dir).await.unwrap();
+            std::fs::remove_dir_all(&dir).unwrap();
+            assert_eq!(blocked, *expected, "{}", name);
+        }
+    }
+}
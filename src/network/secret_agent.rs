@@ -0,0 +1,264 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc, sync::Mutex};
+
+use clap::{Args, ValueEnum};
+use futures_util::StreamExt;
+use zbus::{dbus_interface, fdo::DBusProxy, Connection, DBusError, Proxy};
+use zvariant::{OwnedObjectPath, OwnedValue};
+
+use super::{
+    prompter::{CommandPrompter, Prompter, SecretRequest, SocketPrompter, StdinPrompter},
+    settings::ConnectionSettings,
+};
+use crate::error::Error;
+
+/// Where the secret requests are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PrompterKind {
+    /// Ask on the terminal
+    Stdin,
+    /// Run `--command` for each secret
+    Command,
+    /// Serve the requests as json lines on `--socket`
+    Socket,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct AgentConfig {
+    /// How the user is asked for the secrets
+    #[arg(long, value_enum, default_value_t = PrompterKind::Stdin)]
+    pub prompter: PrompterKind,
+    /// Shell command printing the secret, for the command prompter
+    #[arg(long, required_if_eq("prompter", "command"))]
+    pub command: Option<String>,
+    /// Unix socket path of the socket prompter, `$XDG_RUNTIME_DIR/eww-network-secrets.sock` by default
+    #[arg(long)]
+    pub socket: Option<PathBuf>,
+}
+
+/// Flags of a GetSecrets call
+const ALLOW_INTERACTION: u32 = 0x1;
+const REQUEST_NEW: u32 = 0x2;
+
+#[derive(DBusError, Debug)]
+#[dbus_error(prefix = "org.freedesktop.NetworkManager.SecretAgent")]
+enum SecretAgentError {
+    #[dbus_error(zbus_error)]
+    ZBus(zbus::Error),
+    UserCanceled(String),
+    AgentCanceled(String),
+    NoSecrets(String),
+    InvalidConnection(String),
+}
+
+/// Object called by NetworkManager when a connection needs secrets it does not have
+struct SecretAgent {
+    prompter: Arc<dyn Prompter>,
+    /// The prompters use tokio while the D-Bus methods run on the zbus executor
+    runtime: tokio::runtime::Handle,
+    /// Cancel senders of the running requests, by connection path and setting name
+    pending: Mutex<HashMap<(String, String), tokio::sync::oneshot::Sender<()>>>,
+}
+
+#[dbus_interface(name = "org.freedesktop.NetworkManager.SecretAgent")]
+impl SecretAgent {
+    async fn get_secrets(
+        &self,
+        connection: ConnectionSettings,
+        connection_path: OwnedObjectPath,
+        setting_name: String,
+        hints: Vec<String>,
+        flags: u32,
+    ) -> Result<ConnectionSettings, SecretAgentError> {
+        if flags & ALLOW_INTERACTION == 0 {
+            return Err(SecretAgentError::NoSecrets(
+                "the user can not be asked".to_string(),
+            ));
+        }
+        let request = secret_request(&connection, &setting_name, &hints, flags)?;
+
+        let key = (connection_path.to_string(), setting_name.clone());
+        let (cancel, canceled) = tokio::sync::oneshot::channel();
+        self.pending
+            .lock()
+            .expect("lock poisoned")
+            .insert(key.clone(), cancel);
+
+        let prompter = self.prompter.clone();
+        let prompt = self
+            .runtime
+            .spawn(async move { prompter.prompt(&request).await });
+        let abort = prompt.abort_handle();
+        let answer = tokio::select! {
+            answer = prompt => Some(answer),
+            _ = canceled => None,
+        };
+        self.pending.lock().expect("lock poisoned").remove(&key);
+
+        let secrets = match answer {
+            Some(Ok(Ok(Some(secrets)))) => secrets,
+            Some(Ok(Ok(None))) => {
+                return Err(SecretAgentError::UserCanceled(
+                    "the user canceled the request".to_string(),
+                ))
+            }
+            Some(Ok(Err(err))) => return Err(SecretAgentError::NoSecrets(err.to_string())),
+            Some(Err(err)) => return Err(SecretAgentError::NoSecrets(err.to_string())),
+            None => {
+                abort.abort();
+                return Err(SecretAgentError::AgentCanceled(
+                    "NetworkManager canceled the request".to_string(),
+                ));
+            }
+        };
+
+        // VPN plugins read their secrets from a single string dictionary
+        let values = if setting_name == "vpn" {
+            HashMap::from([("secrets".to_string(), OwnedValue::from(secrets))])
+        } else {
+            secrets
+                .into_iter()
+                .map(|(key, value)| (key, OwnedValue::from(zvariant::Str::from(value))))
+                .collect()
+        };
+        Ok(HashMap::from([(setting_name, values)]))
+    }
+
+    async fn cancel_get_secrets(&self, connection_path: OwnedObjectPath, setting_name: String) {
+        let key = (connection_path.to_string(), setting_name);
+        if let Some(cancel) = self.pending.lock().expect("lock poisoned").remove(&key) {
+            let _ = cancel.send(());
+        }
+    }
+
+    /// Secrets are never stored by the agent, NetworkManager keeps the system-owned ones
+    async fn save_secrets(
+        &self,
+        _connection: ConnectionSettings,
+        _connection_path: OwnedObjectPath,
+    ) {
+    }
+
+    async fn delete_secrets(
+        &self,
+        _connection: ConnectionSettings,
+        _connection_path: OwnedObjectPath,
+    ) {
+    }
+}
+
+/// Describe the secrets NetworkManager asks for
+fn secret_request(
+    connection: &ConnectionSettings,
+    setting_name: &str,
+    hints: &[String],
+    flags: u32,
+) -> Result<SecretRequest, SecretAgentError> {
+    let string_setting = |setting: &str, key: &str| {
+        connection
+            .get(setting)
+            .and_then(|values| values.get(key))
+            .and_then(|value| <&str>::try_from(value).ok())
+            .map(str::to_string)
+    };
+    let id = string_setting("connection", "id").ok_or_else(|| {
+        SecretAgentError::InvalidConnection("the connection has no id".to_string())
+    })?;
+
+    // VPN plugins send "x-vpn-message:<message>" along with the secret names
+    let message = hints
+        .iter()
+        .find_map(|hint| hint.strip_prefix("x-vpn-message:"))
+        .map(str::to_string);
+    let mut keys: Vec<String> = hints
+        .iter()
+        .filter(|hint| !hint.starts_with("x-vpn-"))
+        .cloned()
+        .collect();
+    if keys.is_empty() {
+        let key = match setting_name {
+            "802-11-wireless-security" => {
+                match string_setting(setting_name, "key-mgmt").as_deref() {
+                    Some("none") => "wep-key0",
+                    _ => "psk",
+                }
+            }
+            "802-1x" | "gsm" | "cdma" | "pppoe" | "vpn" => "password",
+            _ => {
+                return Err(SecretAgentError::NoSecrets(format!(
+                    "unsupported setting \"{}\"",
+                    setting_name
+                )))
+            }
+        };
+        keys.push(key.to_string());
+    }
+
+    Ok(SecretRequest {
+        connection: id,
+        connection_type: string_setting("connection", "type").unwrap_or_default(),
+        setting: setting_name.to_string(),
+        keys,
+        message,
+        retry: flags & REQUEST_NEW != 0,
+    })
+}
+
+/// Register a secret agent answering the requests of NetworkManager with `config.prompter`
+pub(crate) async fn agent(config: AgentConfig) -> Result<(), Error> {
+    let prompter: Arc<dyn Prompter> = match config.prompter {
+        PrompterKind::Stdin => Arc::new(StdinPrompter),
+        PrompterKind::Command => Arc::new(CommandPrompter {
+            command: config.command.expect("command is required"),
+        }),
+        PrompterKind::Socket => {
+            let path = match config.socket {
+                Some(path) => path,
+                None => SocketPrompter::default_path()?,
+            };
+            Arc::new(SocketPrompter::bind(&path).await?)
+        }
+    };
+
+    let connection = Connection::system().await?;
+    connection
+        .object_server()
+        .at(
+            "/org/freedesktop/NetworkManager/SecretAgent",
+            SecretAgent {
+                prompter,
+                runtime: tokio::runtime::Handle::current(),
+                pending: Default::default(),
+            },
+        )
+        .await?;
+
+    let agent_manager = Proxy::new(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        "/org/freedesktop/NetworkManager/AgentManager",
+        "org.freedesktop.NetworkManager.AgentManager",
+    )
+    .await?;
+    let dbus = DBusProxy::new(&connection).await?;
+    let mut owner_changed = dbus
+        .receive_name_owner_changed_with_args(&[(0, "org.freedesktop.NetworkManager")])
+        .await?;
+    if dbus
+        .name_has_owner("org.freedesktop.NetworkManager".try_into()?)
+        .await?
+    {
+        agent_manager
+            .call::<_, _, ()>("Register", &"eww_script_utils")
+            .await?;
+    }
+
+    // NetworkManager forgets its agents when it restarts, register again each time it comes back
+    while let Some(signal) = owner_changed.next().await {
+        if signal.args().is_ok_and(|args| args.new_owner().is_some()) {
+            agent_manager
+                .call::<_, _, ()>("Register", &"eww_script_utils")
+                .await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+
+use zbus::{Connection, Proxy, SignalStream};
+use zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use super::access_point::{ssid_to_string, Security};
+use crate::error::Error;
+
+/// Settings of a connection profile, grouped by setting name ("connection", "802-11-wireless", ...)
+pub type ConnectionSettings = HashMap<String, HashMap<String, OwnedValue>>;
+
+/// Settings sent to NetworkManager to create a new connection profile
+pub type NewConnectionSettings<'a> = HashMap<&'a str, HashMap<&'a str, Value<'a>>>;
+
+/// Settings of a Wi-Fi infrastructure profile, secured as `security` require when a password is
+/// given, with WPA-PSK when the security is unknown.
+/// A `hidden` network is probed for since it does not show up in the scans.
+pub fn wireless_settings<'a>(
+    ssid: &'a str,
+    password: Option<&'a str>,
+    hidden: bool,
+    security: Option<Security>,
+) -> Result<NewConnectionSettings<'a>, Error> {
+    let mut settings = HashMap::new();
+    settings.insert(
+        "connection",
+        HashMap::from([
+            ("id", Value::from(ssid)),
+            ("type", Value::from("802-11-wireless")),
+        ]),
+    );
+    settings.insert(
+        "802-11-wireless",
+        HashMap::from([
+            ("ssid", Value::from(ssid.as_bytes())),
+            ("mode", Value::from("infrastructure")),
+            ("hidden", Value::from(hidden)),
+        ]),
+    );
+    if let Some(password) = password {
+        let security_settings = match security.unwrap_or(Security::Wpa2Psk) {
+            Security::Open => {
+                return Err(format!("\"{}\" is an open network, it takes no password", ssid).into())
+            }
+            Security::Enterprise => {
+                return Err(format!("\"{}\" needs a 802.1X profile, not a password", ssid).into())
+            }
+            Security::Wep => HashMap::from([
+                ("key-mgmt", Value::from("none")),
+                ("wep-key0", Value::from(password)),
+                // The key itself, in hexadecimal or ASCII
+                ("wep-key-type", Value::from(1u32)),
+            ]),
+            Security::WpaPsk | Security::Wpa2Psk => HashMap::from([
+                ("key-mgmt", Value::from("wpa-psk")),
+                ("psk", Value::from(password)),
+            ]),
+            Security::Wpa3Sae => HashMap::from([
+                ("key-mgmt", Value::from("sae")),
+                ("psk", Value::from(password)),
+            ]),
+        };
+        settings.insert("802-11-wireless-security", security_settings);
+    }
+    Ok(settings)
+}
+
+/// Settings of a Wi-Fi access point profile sharing the connection of the host,
+/// secured with WPA2-PSK when a password is given
+pub fn hotspot_settings<'a>(
+    id: &'a str,
+    ssid: &'a str,
+    band: &'a str,
+    password: Option<&'a str>,
+) -> NewConnectionSettings<'a> {
+    let mut settings = HashMap::new();
+    settings.insert(
+        "connection",
+        HashMap::from([
+            ("id", Value::from(id)),
+            ("type", Value::from("802-11-wireless")),
+            ("autoconnect", Value::from(false)),
+        ]),
+    );
+    settings.insert(
+        "802-11-wireless",
+        HashMap::from([
+            ("ssid", Value::from(ssid.as_bytes())),
+            ("mode", Value::from("ap")),
+            ("band", Value::from(band)),
+        ]),
+    );
+    if let Some(password) = password {
+        settings.insert(
+            "802-11-wireless-security",
+            HashMap::from([
+                ("key-mgmt", Value::from("wpa-psk")),
+                ("proto", Value::from(vec!["rsn"])),
+                ("pairwise", Value::from(vec!["ccmp"])),
+                ("group", Value::from(vec!["ccmp"])),
+                ("psk", Value::from(password)),
+            ]),
+        );
+    }
+    settings.insert("ipv4", HashMap::from([("method", Value::from("shared"))]));
+    settings.insert("ipv6", HashMap::from([("method", Value::from("ignore"))]));
+    settings
+}
+
+pub struct Settings<'a> {
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Settings"
+    proxy: Proxy<'a>,
+    connection: &'a Connection,
+}
+
+impl Settings<'_> {
+    pub async fn new(connection: &Connection) -> Result<Settings<'_>, Error> {
+        let p = Proxy::new(
+            connection,
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager/Settings",
+            "org.freedesktop.NetworkManager.Settings",
+        )
+        .await?;
+        Ok(Settings {
+            proxy: p,
+            connection,
+        })
+    }
+
+    pub async fn list_connections(&self) -> Result<Vec<SettingsConnection<'_>>, Error> {
+        let connections_path: Vec<OwnedObjectPath> =
+            self.proxy.call("ListConnections", &()).await?;
+        let mut connections = vec![];
+        for connection_path in connections_path {
+            connections.push(SettingsConnection::new(connection_path, self.connection).await?)
+        }
+        Ok(connections)
+    }
+
+    pub async fn receive_new_connection(&self) -> Result<SignalStream<'static>, Error> {
+        Ok(self.proxy.receive_signal("NewConnection").await?)
+    }
+
+    pub async fn receive_connection_removed(&self) -> Result<SignalStream<'static>, Error> {
+        Ok(self.proxy.receive_signal("ConnectionRemoved").await?)
+    }
+
+    /// First saved profile whose id (the name shown to the user) is `id`
+    pub async fn get_connection_by_id(
+        &self,
+        id: &str,
+    ) -> Result<Option<SettingsConnection<'_>>, Error> {
+        for connection in self.list_connections().await? {
+            if connection.get_id().await? == id {
+                return Ok(Some(connection));
+            }
+        }
+        Ok(None)
+    }
+
+    /// First saved Wi-Fi profile matching `ssid`
+    pub async fn get_connection_by_ssid(
+        &self,
+        ssid: &str,
+    ) -> Result<Option<SettingsConnection<'_>>, Error> {
+        for connection in self.list_connections().await? {
+            if connection.get_ssid().await?.as_deref() == Some(ssid) {
+                return Ok(Some(connection));
+            }
+        }
+        Ok(None)
+    }
+
+    /// SSIDs of every saved Wi-Fi profile
+    pub async fn get_saved_ssids(&self) -> Result<Vec<String>, Error> {
+        let mut ssids = vec![];
+        for connection in self.list_connections().await? {
+            if let Some(ssid) = connection.get_ssid().await? {
+                ssids.push(ssid);
+            }
+        }
+        Ok(ssids)
+    }
+}
+
+pub struct SettingsConnection<'a> {
+    path: String,
+    /// Proxy pointing on "org.freedesktop.NetworkManager.Settings.Connection"
+    proxy: Proxy<'a>,
+}
+
+impl SettingsConnection<'_> {
+    pub async fn new(
+        path: OwnedObjectPath,
+        connection: &Connection,
+    ) -> Result<SettingsConnection<'_>, Error> {
+        let p = Proxy::new(
+            connection,
+            "org.freedesktop.NetworkManager",
+            path.clone(),
+            "org.freedesktop.NetworkManager.Settings.Connection",
+        )
+        .await?;
+        Ok(SettingsConnection {
+            path: path.to_string(),
+            proxy: p,
+        })
+    }
+
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    pub async fn get_settings(&self) -> Result<ConnectionSettings, Error> {
+        Ok(self.proxy.call("GetSettings", &()).await?)
+    }
+
+    /// Read every setting at once, to get many values from a single GetSettings call
+    pub async fn get_profile_settings(&self) -> Result<ProfileSettings, Error> {
+        Ok(ProfileSettings(self.get_settings().await?))
+    }
+
+    /// Read a string setting, `None` when the setting is not present in the profile
+    pub async fn get_string_setting(
+        &self,
+        setting: &str,
+        key: &str,
+    ) -> Result<Option<String>, Error> {
+        self.get_profile_settings()
+            .await?
+            .get_string_setting(setting, key)
+    }
+
+    pub async fn get_id(&self) -> Result<String, Error> {
+        self.get_profile_settings().await?.get_id()
+    }
+
+    pub async fn get_uuid(&self) -> Result<String, Error> {
+        self.get_profile_settings().await?.get_uuid()
+    }
+
+    /// Type of the profile ("802-11-wireless", "802-3-ethernet", "vpn", ...)
+    pub async fn get_type(&self) -> Result<String, Error> {
+        self.get_profile_settings().await?.get_type()
+    }
+
+    /// Replace the settings of the profile, the secrets left out are kept
+    pub async fn update2(&self, settings: ConnectionSettings) -> Result<(), Error> {
+        // No flag keeps the profile stored where it is
+        let flags = 0u32;
+        let args: HashMap<&str, Value<'_>> = HashMap::new();
+        self.proxy
+            .call::<_, _, HashMap<String, OwnedValue>>("Update2", &(settings, flags, args))
+            .await?;
+        Ok(())
+    }
+
+    /// Delete the profile
+    pub async fn delete(&self) -> Result<(), Error> {
+        self.proxy.call::<_, _, ()>("Delete", &()).await?;
+        Ok(())
+    }
+
+    pub async fn set_autoconnect(&self, autoconnect: bool) -> Result<(), Error> {
+        let mut settings = self.get_settings().await?;
+        settings
+            .entry("connection".to_string())
+            .or_default()
+            .insert("autoconnect".to_string(), OwnedValue::from(autoconnect));
+        self.update2(settings).await
+    }
+
+    /// Replace the password of a secured Wi-Fi profile.
+    /// Fail when the password is not stored in the profile but owned by a secret agent,
+    /// or asked on each connection, since NetworkManager would save it anyway.
+    pub async fn set_wireless_password(&self, password: &str) -> Result<(), Error> {
+        let mut settings = self.get_settings().await?;
+        let security = settings
+            .get_mut("802-11-wireless-security")
+            .ok_or("the saved profile is not secured by a password")?;
+        let key_mgmt = match security.get("key-mgmt") {
+            Some(key_mgmt) => <&str>::try_from(key_mgmt)?.to_string(),
+            None => String::new(),
+        };
+        let (key, flags_key) = match key_mgmt.as_str() {
+            "none" => ("wep-key0", "wep-key-flags"),
+            "wpa-psk" | "sae" => ("psk", "psk-flags"),
+            key_mgmt => {
+                return Err(format!("can't set the password of a \"{}\" profile", key_mgmt).into())
+            }
+        };
+        // NMSettingSecretFlags, 0 when NetworkManager store the secret itself
+        let flags = match security.get(flags_key) {
+            Some(flags) => u32::try_from(flags)?,
+            None => 0,
+        };
+        if flags != 0 {
+            return Err(format!(
+                "the password of the saved profile is not stored by NetworkManager ({} {})",
+                flags_key, flags
+            )
+            .into());
+        }
+        security.insert(
+            key.to_string(),
+            OwnedValue::from(zvariant::Str::from(password)),
+        );
+        self.update2(settings).await
+    }
+
+    /// SSID of the profile, `None` if it is not a Wi-Fi profile
+    pub async fn get_ssid(&self) -> Result<Option<String>, Error> {
+        self.get_profile_settings().await?.get_ssid()
+    }
+}
+
+/// Settings of a profile as read by [`SettingsConnection::get_profile_settings`]
+pub struct ProfileSettings(ConnectionSettings);
+
+impl ProfileSettings {
+    /// Read a string setting, `None` when the setting is not present in the profile
+    pub fn get_string_setting(&self, setting: &str, key: &str) -> Result<Option<String>, Error> {
+        match self.0.get(setting).and_then(|values| values.get(key)) {
+            Some(value) => Ok(Some(<&str>::try_from(value)?.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_id(&self) -> Result<String, Error> {
+        Ok(self
+            .get_string_setting("connection", "id")?
+            .unwrap_or_default())
+    }
+
+    pub fn get_uuid(&self) -> Result<String, Error> {
+        Ok(self
+            .get_string_setting("connection", "uuid")?
+            .unwrap_or_default())
+    }
+
+    /// Type of the profile ("802-11-wireless", "802-3-ethernet", "vpn", ...)
+    pub fn get_type(&self) -> Result<String, Error> {
+        Ok(self
+            .get_string_setting("connection", "type")?
+            .unwrap_or_default())
+    }
+
+    /// Whether NetworkManager activate the profile by itself, true when not set
+    pub fn is_autoconnect(&self) -> Result<bool, Error> {
+        match self
+            .0
+            .get("connection")
+            .and_then(|connection| connection.get("autoconnect"))
+        {
+            Some(autoconnect) => Ok(bool::try_from(autoconnect)?),
+            None => Ok(true),
+        }
+    }
+
+    /// Seconds since the Unix Epoch of the last successful activation, 0 when never used
+    pub fn get_timestamp(&self) -> Result<u64, Error> {
+        match self
+            .0
+            .get("connection")
+            .and_then(|connection| connection.get("timestamp"))
+        {
+            Some(timestamp) => Ok(u64::try_from(timestamp)?),
+            None => Ok(0),
+        }
+    }
+
+    /// SSID of the profile, `None` if it is not a Wi-Fi profile
+    pub fn get_ssid(&self) -> Result<Option<String>, Error> {
+        let ssid_raw = match self
+            .0
+            .get("802-11-wireless")
+            .and_then(|wireless| wireless.get("ssid"))
+        {
+            Some(ssid_raw) => Vec::<u8>::try_from(ssid_raw.clone())?,
+            None => return Ok(None),
+        };
+        Ok(Some(ssid_to_string(&ssid_raw)))
+    }
+}
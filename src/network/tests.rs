@@ -0,0 +1,419 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use zbus::Connection;
+
+use crate::error::Error;
+
+use super::{
+    access_point::{AccessPoints, Security},
+    connectivity::{self, ProbeConfig, ProbeKind},
+    device::{Device, NMDeviceType},
+    group_access_points,
+    mock::{
+        access_point_path, set_strength, MockAccessPoint, MockActiveConnection, MockBus,
+        MockDevice, MockIpConfig, MockNetworkManager,
+    },
+    modem::ModemManager,
+    networkmanager::{NMConnectivityState, NMState, NetworkManager},
+    receive_properties_changed, receive_signals, state_info, watch_service,
+};
+
+/// A bus serving `mock`, with the service and a client connection.
+/// `None` when dbus-daemon is not installed, the test is then skipped.
+async fn serve(mock: &MockNetworkManager) -> Option<(MockBus, Connection, Connection)> {
+    let Some(bus) = MockBus::start() else {
+        eprintln!("dbus-daemon not found, skipping");
+        return None;
+    };
+    let service = mock.serve(&bus).await.expect("serve the mock");
+    let client = bus.connect().await.expect("connect to the mock bus");
+    Some((bus, service, client))
+}
+
+/// Connected to "home" over Wi-Fi, with an unplugged Ethernet port
+fn home_wifi() -> MockNetworkManager {
+    let mut ethernet = MockDevice::ethernet("enp0s31f6");
+    ethernet.state = 20;
+    let mut home = MockActiveConnection::new("home", "802-11-wireless", vec![0]);
+    home.default = true;
+    home.ip4 = Some(MockIpConfig {
+        gateway: "192.168.1.1".to_string(),
+        addresses: vec![("192.168.1.20".to_string(), 24)],
+    });
+    home.ip6 = Some(MockIpConfig {
+        gateway: "fe80::1".to_string(),
+        addresses: vec![
+            ("fe80::20".to_string(), 64),
+            ("2001:db8::20".to_string(), 64),
+        ],
+    });
+    MockNetworkManager::new(
+        vec![
+            MockDevice::wifi(
+                "wlan0",
+                vec![
+                    MockAccessPoint::new(b"home", 74),
+                    MockAccessPoint::new(b"neighbour", 30),
+                ],
+            ),
+            ethernet,
+        ],
+        vec![home],
+    )
+}
+
+fn nm_probe() -> ProbeConfig {
+    ProbeConfig {
+        kind: ProbeKind::NetworkManager,
+        hosts: vec![],
+        timeout_ms: 1000,
+        retries: 0,
+        expected_body: None,
+        interval_ms: 30000,
+    }
+}
+
+/// Json [`super::info`] would print
+async fn info_json(client: &Connection) -> serde_json::Value {
+    let nm = NetworkManager::new(client).await.unwrap();
+    let state = nm.get_state().await.unwrap();
+    let pc = nm.get_primary_connection().await.unwrap();
+    let probe_result = connectivity::probe(&nm_probe(), &nm).await;
+    state_info(&nm, pc, state, false, probe_result)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn network_manager_state_and_connectivity() {
+    let Some((_bus, _service, client)) = serve(&home_wifi()).await else {
+        return;
+    };
+    let nm = NetworkManager::new(&client).await.unwrap();
+
+    assert_eq!(nm.get_state().await.unwrap(), NMState::ConnectedGlobal);
+    assert_eq!(
+        nm.get_connectivity().await.unwrap(),
+        NMConnectivityState::Full
+    );
+    assert!(nm.check_connectivity(true).await.unwrap());
+    assert!(nm.is_networking_enable().await.unwrap());
+}
+
+#[tokio::test]
+async fn devices_are_typed() {
+    let Some((_bus, _service, client)) = serve(&home_wifi()).await else {
+        return;
+    };
+    let nm = NetworkManager::new(&client).await.unwrap();
+
+    let devices = nm.get_devices().await.unwrap();
+    assert_eq!(devices.len(), 2);
+    assert!(matches!(devices[0], Device::WirelessDevice(_)));
+    assert!(matches!(devices[1], Device::WiredDevice(_)));
+    assert_eq!(
+        devices[1].get_device_type().await.unwrap(),
+        NMDeviceType::Ethernet
+    );
+    assert_eq!(devices[1].get_interface().await.unwrap(), "enp0s31f6");
+
+    let wireless_devices = nm.get_wireless_devices().await.unwrap();
+    assert_eq!(wireless_devices.len(), 1);
+    assert_eq!(wireless_devices[0].get_interface().await.unwrap(), "wlan0");
+}
+
+#[tokio::test]
+async fn access_points_of_wireless_device() {
+    let Some((_bus, _service, client)) = serve(&home_wifi()).await else {
+        return;
+    };
+    let nm = NetworkManager::new(&client).await.unwrap();
+    let wireless_devices = nm.get_wireless_devices().await.unwrap();
+
+    let access_points = wireless_devices[0].get_access_points().await.unwrap();
+    let mut ssids = vec![];
+    for access_point in &access_points {
+        ssids.push(access_point.get_ssid().await.unwrap());
+    }
+    assert_eq!(ssids, ["home", "neighbour"]);
+
+    let active = wireless_devices[0].get_active_access_point().await.unwrap();
+    assert_eq!(active.get_ssid().await.unwrap(), "home");
+    assert_eq!(active.get_strength().await.unwrap(), 74);
+    assert_eq!(active.get_security().await.unwrap(), Security::Open);
+}
+
+#[tokio::test]
+async fn active_connections_with_devices_and_addresses() {
+    let Some((_bus, _service, client)) = serve(&home_wifi()).await else {
+        return;
+    };
+    let nm = NetworkManager::new(&client).await.unwrap();
+
+    let active_connections = nm.get_active_connections().await.unwrap();
+    assert_eq!(active_connections.len(), 1);
+    let home = &active_connections[0];
+    assert_eq!(home.get_id().await.unwrap(), "home");
+    assert_eq!(home.get_type().await.unwrap(), "802-11-wireless");
+    assert!(home.is_default().await.unwrap());
+    assert!(!home.is_vpn().await.unwrap());
+
+    let devices = home.get_devices().await.unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].get_interface().await.unwrap(), "wlan0");
+
+    let ip4_config = home.get_ip4_config().await.unwrap();
+    assert_eq!(ip4_config.get_gateway().await.unwrap(), "192.168.1.1");
+    let addresses = ip4_config.get_addresses().await.unwrap();
+    assert_eq!(addresses.len(), 1);
+    assert_eq!(addresses[0].address, "192.168.1.20");
+    assert_eq!(addresses[0].mask_cird, "24");
+
+    let primary = nm.get_primary_connection().await.unwrap();
+    assert_eq!(primary.get_path(), home.get_path());
+}
+
+#[tokio::test]
+async fn info_of_wireless_connection() {
+    let Some((_bus, _service, client)) = serve(&home_wifi()).await else {
+        return;
+    };
+
+    let info = info_json(&client).await;
+    assert_eq!(info["state"], "ConnectedGlobal");
+    assert_eq!(info["interface"], "wlan0");
+    assert_eq!(info["kind"], "wireless");
+    assert_eq!(info["ssid"], "home");
+    assert_eq!(info["signalStrength"], 74);
+    assert_eq!(info["frequency"], 2412);
+    assert_eq!(info["ipaddr"], "192.168.1.20");
+    assert_eq!(info["cidr"], "24");
+    assert_eq!(info["gateway"], "192.168.1.1");
+    // The routable address is preferred over the link-local one
+    assert_eq!(info["ip6addr"], "2001:db8::20");
+    assert_eq!(info["captivePortal"], false);
+    assert_eq!(info["checkUrl"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+async fn info_behind_captive_portal() {
+    let mut mock = home_wifi();
+    mock.connectivity = 2;
+    mock.connectivity_check_uri = "http://portal.example/".to_string();
+    let Some((_bus, _service, client)) = serve(&mock).await else {
+        return;
+    };
+
+    let info = info_json(&client).await;
+    assert_eq!(info["state"], "ConnectedLocal");
+    assert_eq!(info["captivePortal"], true);
+    assert_eq!(info["checkUrl"], "http://portal.example/");
+}
+
+#[tokio::test]
+async fn info_without_primary_connection() {
+    let mut ethernet = MockDevice::ethernet("enp0s31f6");
+    ethernet.state = 30;
+    let Some((_bus, _service, client)) =
+        serve(&MockNetworkManager::new(vec![ethernet], vec![])).await
+    else {
+        return;
+    };
+
+    let info = info_json(&client).await;
+    assert_eq!(info, serde_json::json!({ "state": "Disconnected" }));
+}
+
+#[tokio::test]
+async fn info_of_non_utf8_ssid() {
+    let mut mock = home_wifi();
+    mock.devices[0].access_points[0].ssid = b"caf\xe9".to_vec();
+    let Some((_bus, _service, client)) = serve(&mock).await else {
+        return;
+    };
+
+    let info = info_json(&client).await;
+    assert_eq!(info["ssid"], "caf\u{fffd}");
+    assert_eq!(info["ssidHex"], "636166e9");
+}
+
+#[tokio::test]
+async fn properties_changed_stream() {
+    let Some((_bus, service, client)) = serve(&home_wifi()).await else {
+        return;
+    };
+    let mut strength_changed = receive_properties_changed(
+        &client,
+        "/org/freedesktop/NetworkManager/AccessPoint",
+        "org.freedesktop.NetworkManager.AccessPoint",
+        &["Strength"],
+    )
+    .await
+    .unwrap();
+
+    set_strength(&service, access_point_path(0), 50)
+        .await
+        .unwrap();
+    let changed = tokio::time::timeout(Duration::from_secs(5), strength_changed.next()).await;
+    assert_eq!(changed, Ok(Some(())));
+
+    let info = info_json(&client).await;
+    assert_eq!(info["signalStrength"], 50);
+}
+
+#[tokio::test]
+async fn signal_stream_of_namespace() {
+    let Some((_bus, service, client)) = serve(&home_wifi()).await else {
+        return;
+    };
+    let mut updated = receive_signals(
+        &client,
+        "/org/freedesktop/NetworkManager/Settings",
+        "org.freedesktop.NetworkManager.Settings.Connection",
+        "Updated",
+    )
+    .await
+    .unwrap();
+
+    for path in [
+        "/org/freedesktop/NetworkManager/Devices/1",
+        "/org/freedesktop/NetworkManager/Settings/1",
+    ] {
+        service
+            .emit_signal(
+                None::<()>,
+                path,
+                "org.freedesktop.NetworkManager.Settings.Connection",
+                "Updated",
+                &(),
+            )
+            .await
+            .unwrap();
+    }
+    let received = tokio::time::timeout(Duration::from_secs(5), updated.next()).await;
+    assert_eq!(received, Ok(Some(())));
+    // The signal outside of the namespace is filtered out
+    let received = tokio::time::timeout(Duration::from_millis(200), updated.next()).await;
+    assert!(received.is_err());
+}
+
+#[tokio::test]
+async fn vanished_object_error() {
+    let Some((_bus, _service, client)) = serve(&home_wifi()).await else {
+        return;
+    };
+
+    let access_point = AccessPoints::new(access_point_path(99), &client)
+        .await
+        .unwrap();
+    let error = access_point.get_strength().await.unwrap_err();
+    assert!(matches!(error, Error::ObjectVanished(_)), "{:?}", error);
+    assert!(error.is_recoverable());
+    assert_eq!(error.to_json()["error"]["kind"], "objectVanished");
+}
+
+#[tokio::test]
+async fn service_missing_error() {
+    let Some((_bus, _service, client)) = serve(&home_wifi()).await else {
+        return;
+    };
+
+    // Only NetworkManager is mocked
+    let mm = ModemManager::new(&client).await.unwrap();
+    let Err(error) = mm.get_modems().await else {
+        panic!("ModemManager is not on the bus");
+    };
+    assert!(matches!(error, Error::ServiceMissing(_)), "{:?}", error);
+    assert!(!error.is_recoverable());
+}
+
+#[tokio::test]
+async fn listener_restarted_with_service() {
+    let mock = home_wifi();
+    let Some((bus, service, client)) = serve(&mock).await else {
+        return;
+    };
+    let (started, mut starts) = tokio::sync::mpsc::unbounded_channel();
+    let watch = watch_service(
+        &client,
+        "org.freedesktop.NetworkManager",
+        serde_json::Value::Null,
+        || {
+            let started = started.clone();
+            async move {
+                started.send(()).unwrap();
+                std::future::pending::<Result<(), Error>>().await
+            }
+        },
+    );
+    let restart = async {
+        starts.recv().await;
+        // NetworkManager leaves the bus then comes back
+        drop(service);
+        let _service = mock.serve(&bus).await.unwrap();
+        starts.recv().await;
+    };
+
+    tokio::select! {
+        result = watch => panic!("watch_service stopped: {:?}", result),
+        restarted = tokio::time::timeout(Duration::from_secs(5), restart) => {
+            assert!(restarted.is_ok(), "listener not restarted");
+        }
+    }
+}
+
+#[test]
+fn access_points_grouped_by_ssid() {
+    let access_point = |ssid: &str, bssid: &str, active: bool| {
+        serde_json::json!({
+            "ssidHex": ssid,
+            "hidden": ssid.is_empty(),
+            "bssid": bssid,
+            "active": active,
+        })
+    };
+    let cases = [
+        (vec![], vec![]),
+        (
+            vec![
+                access_point("aa", "1", false),
+                access_point("bb", "2", false),
+            ],
+            vec![("aa", vec!["1"], false), ("bb", vec!["2"], false)],
+        ),
+        // The strongest comes first and stays the visible one, the active flag is merged
+        (
+            vec![
+                access_point("aa", "1", false),
+                access_point("bb", "2", false),
+                access_point("aa", "3", true),
+            ],
+            vec![("aa", vec!["1", "3"], true), ("bb", vec!["2"], false)],
+        ),
+        // Hidden networks can not be told apart
+        (
+            vec![access_point("", "1", false), access_point("", "2", false)],
+            vec![("", vec!["1"], false), ("", vec!["2"], false)],
+        ),
+    ];
+    for (access_points, expected) in cases {
+        let networks = group_access_points(access_points);
+        let networks: Vec<_> = networks
+            .iter()
+            .map(|network| {
+                (
+                    network["ssidHex"].as_str().unwrap(),
+                    network["bssids"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|bssid| bssid.as_str().unwrap())
+                        .collect::<Vec<_>>(),
+                    network["active"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(networks, expected);
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use serde_json::json;
+use zbus::Connection;
+
+use super::networkmanager::NetworkManager;
+use crate::error::Error;
+
+#[derive(Debug, Clone, Args)]
+pub struct TrafficConfig {
+    /// Time between two samples in milliseconds
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+    /// Interface to monitor instead of the one of the primary connection
+    #[arg(long)]
+    pub interface: Option<String>,
+}
+
+/// Byte counters of an interface at a given time
+struct TrafficSample {
+    interface: String,
+    rx_bytes: u64,
+    tx_bytes: u64,
+    time: Instant,
+}
+
+impl TrafficSample {
+    async fn read(interface: &str) -> Result<TrafficSample, Error> {
+        let (rx_bytes, tx_bytes) = tokio::try_join!(
+            read_counter(interface, "rx_bytes"),
+            read_counter(interface, "tx_bytes"),
+        )?;
+        Ok(TrafficSample {
+            interface: interface.to_string(),
+            rx_bytes,
+            tx_bytes,
+            time: Instant::now(),
+        })
+    }
+}
+
+async fn read_counter(interface: &str, counter: &str) -> Result<u64, Error> {
+    let path = format!("/sys/class/net/{}/statistics/{}", interface, counter);
+    Ok(tokio::fs::read_to_string(path).await?.trim().parse()?)
+}
+
+/// "1.5 MiB/s" like representation of a rate in bytes per second
+fn format_rate(bytes_per_sec: f64) -> String {
+    const UNITS: [&str; 5] = ["B/s", "KiB/s", "MiB/s", "GiB/s", "TiB/s"];
+    let mut rate = bytes_per_sec;
+    let mut unit = 0;
+    while rate >= 1024.0 && unit < UNITS.len() - 1 {
+        rate /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{:.0} {}", rate, UNITS[unit]),
+        _ => format!("{:.1} {}", rate, UNITS[unit]),
+    }
+}
+
+/// Interface of the first device of the primary connection
+async fn primary_interface(nm: &NetworkManager<'_>) -> Result<String, Error> {
+    let pc = nm.get_primary_connection().await?;
+    let pc_devices = pc.get_devices().await?;
+    let pc_device = pc_devices
+        .first()
+        .ok_or("primary connection without device")?;
+    pc_device.get_interface().await
+}
+
+/// Output a json with the download (rx) and upload (tx) rates of the primary interface
+/// at each interval
+///
+/// ``` json
+/// {
+///   "interface": "wlan0",
+///   "rx": 1572864,
+///   "tx": 20480,
+///   "rxHuman": "1.5 MiB/s",
+///   "txHuman": "20.0 KiB/s"
+/// }
+/// ```
+pub(crate) async fn listener(config: TrafficConfig) -> Result<(), Error> {
+    // NetworkManager is only needed to follow the primary connection
+    let connection = match config.interface {
+        Some(_) => None,
+        None => Some(Connection::system().await?),
+    };
+    let nm = match &connection {
+        Some(connection) => Some(NetworkManager::new(connection).await?),
+        None => None,
+    };
+
+    let mut previous: Option<TrafficSample> = None;
+    let mut interval = tokio::time::interval(Duration::from_millis(config.interval));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        // The primary connection can move from one device to another
+        let interface = match (&config.interface, &nm) {
+            (Some(interface), _) => Some(interface.clone()),
+            (None, Some(nm)) => primary_interface(nm).await.ok(),
+            (None, None) => None,
+        };
+        let sample = match interface {
+            Some(interface) => TrafficSample::read(&interface).await.ok(),
+            None => None,
+        };
+        let Some(sample) = sample else {
+            // Nothing to monitor while disconnected
+            previous = None;
+            println!(
+                "{}",
+                json!({
+                    "interface": null,
+                    "rx": 0,
+                    "tx": 0,
+                    "rxHuman": format_rate(0.0),
+                    "txHuman": format_rate(0.0),
+                })
+            );
+            continue;
+        };
+
+        let (rx, tx) = match &previous {
+            Some(previous) if previous.interface == sample.interface => {
+                let elapsed = sample.time.duration_since(previous.time).as_secs_f64();
+                // Counters are reset when the interface is recreated
+                let rx = sample.rx_bytes.saturating_sub(previous.rx_bytes) as f64 / elapsed;
+                let tx = sample.tx_bytes.saturating_sub(previous.tx_bytes) as f64 / elapsed;
+                (rx, tx)
+            }
+            _ => (0.0, 0.0),
+        };
+        println!(
+            "{}",
+            json!({
+                "interface": sample.interface,
+                "rx": rx.round() as u64,
+                "tx": tx.round() as u64,
+                "rxHuman": format_rate(rx),
+                "txHuman": format_rate(tx),
+            })
+        );
+        previous = Some(sample);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_formatting() {
+        let cases = [
+            (0.0, "0 B/s"),
+            (512.4, "512 B/s"),
+            (1023.0, "1023 B/s"),
+            (1024.0, "1.0 KiB/s"),
+            (1536.0, "1.5 KiB/s"),
+            (5.0 * 1024.0 * 1024.0, "5.0 MiB/s"),
+            (2.5 * 1024.0 * 1024.0 * 1024.0, "2.5 GiB/s"),
+            // No unit above TiB/s
+            (2048.0 * 1024.0_f64.powi(4), "2048.0 TiB/s"),
+        ];
+        for (bytes_per_sec, expected) in cases {
+            assert_eq!(format_rate(bytes_per_sec), expected, "{}", bytes_per_sec);
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::fmt::Display;
+
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use zbus::{Connection, Proxy};
+use zvariant::{OwnedObjectPath, OwnedValue};
+
+use super::{
+    active_connection::ActiveConnection, follow_activation, networkmanager::NetworkManager,
+    receive_properties_changed, settings::Settings, watch_service,
+};
+use crate::error::{report, Error};
+
+#[derive(Debug, OwnedValue, Clone, Copy, PartialEq, Eq)]
+pub enum NMVpnConnectionState {
+    /// The state of the VPN connection is unknown.
+    Unknown = 0,
+    /// The VPN connection is preparing to connect.
+    Prepare = 1,
+    /// The VPN connection needs authorization credentials.
+    NeedAuth = 2,
+    /// The VPN connection is being established.
+    Connect = 3,
+    /// The VPN connection is getting an IP address.
+    IpConfigGet = 4,
+    /// The VPN connection is active.
+    Activated = 5,
+    /// The VPN connection failed.
+    Failed = 6,
+    /// The VPN connection is disconnected.
+    Disconnected = 7,
+}
+
+impl Display for NMVpnConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Extra informations of an active connection backed by a VPN plugin
+pub struct VpnConnection<'a> {
+    path: String,
+    /// Proxy pointing on "org.freedesktop.NetworkManager.VPN.Connection"
+    proxy: Proxy<'a>,
+}
+
+impl VpnConnection<'_> {
+    pub async fn new(
+        path: OwnedObjectPath,
+        connection: &Connection,
+    ) -> Result<VpnConnection<'_>, Error> {
+        let p = Proxy::new(
+            connection,
+            "org.freedesktop.NetworkManager",
+            path.clone(),
+            "org.freedesktop.NetworkManager.VPN.Connection",
+        )
+        .await?;
+        Ok(VpnConnection {
+            path: path.to_string(),
+            proxy: p,
+        })
+    }
+
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    /// The banner string of the VPN connection
+    pub async fn get_banner(&self) -> Result<String, Error> {
+        Ok(self.proxy.get_property::<String>("Banner").await?)
+    }
+
+    pub async fn get_vpn_state(&self) -> Result<NMVpnConnectionState, Error> {
+        Ok(self
+            .proxy
+            .get_property::<NMVpnConnectionState>("VpnState")
+            .await?)
+    }
+}
+
+/// Output a json whenever a VPN or WireGuard connection change
+/// No connection is output while NetworkManager is not running.
+/// The state of a VPN plugin connection details the activation ("NeedAuth", "Connect",
+/// "IpConfigGet"...), the gateways are the ones of the tunnel IP configuration, not the VPN server.
+///
+/// ``` json
+/// {
+///   "active": true,
+///   "connections": [
+///     {
+///       "name": "work",
+///       "type": "openvpn",
+///       "state": "Activated",
+///       "banner": "Welcome",
+///       "ip4Gateway": "10.8.0.1",
+///       "ip6Gateway": "",
+///       "addresses": ["10.8.0.6/24", "fd00:8::6/64"]
+///     }
+///   ]
+/// }
+/// ```
+pub(crate) async fn listener() -> Result<(), Error> {
+    let connection = Connection::system().await?;
+    watch_service(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        json!({ "active": false, "connections": [] }),
+        || listen(&connection),
+    )
+    .await
+}
+
+async fn listen(connection: &Connection) -> Result<(), Error> {
+    let nm = NetworkManager::new(connection).await?;
+
+    let mut events = futures_util::stream::select_all(vec![
+        nm.proxy
+            .receive_active_connections_changed()
+            .await
+            .map(|_| ())
+            .boxed(),
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/ActiveConnection",
+            "org.freedesktop.NetworkManager.Connection.Active",
+            &["State", "Ip4Config", "Ip6Config"],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/ActiveConnection",
+            "org.freedesktop.NetworkManager.VPN.Connection",
+            &["VpnState", "Banner"],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/IP4Config",
+            "org.freedesktop.NetworkManager.IP4Config",
+            &[],
+        )
+        .await?,
+        receive_properties_changed(
+            connection,
+            "/org/freedesktop/NetworkManager/IP6Config",
+            "org.freedesktop.NetworkManager.IP6Config",
+            &[],
+        )
+        .await?,
+    ]);
+
+    report(print_vpn(&nm, connection).await)?;
+    while events.next().await.is_some() {
+        report(print_vpn(&nm, connection).await)?;
+    }
+
+    Ok(())
+}
+
+async fn print_vpn(nm: &NetworkManager<'_>, connection: &Connection) -> Result<(), Error> {
+    let mut vpns = vec![];
+    for active_connection in nm.get_active_connections().await? {
+        // Connections can vanish between the listing and the property reads
+        if let Ok(Some(vpn)) = vpn_data(&active_connection, connection).await {
+            vpns.push(vpn);
+        }
+    }
+    println!(
+        "{}",
+        json!({
+            "active": vpns.iter().any(|vpn| vpn["state"] == "Activated"),
+            "connections": vpns,
+        })
+    );
+    Ok(())
+}
+
+/// Json description of `active_connection`, `None` when it is neither a VPN nor WireGuard
+async fn vpn_data(
+    active_connection: &ActiveConnection<'_>,
+    connection: &Connection,
+) -> Result<Option<Value>, Error> {
+    let connection_type = active_connection.get_type().await?;
+    let is_vpn = active_connection.is_vpn().await?;
+    if !is_vpn && connection_type != "wireguard" {
+        return Ok(None);
+    }
+
+    let (kind, state, banner) = if is_vpn {
+        let settings_connection = active_connection.get_settings_connection().await?;
+        // "org.freedesktop.NetworkManager.openvpn" => "openvpn"
+        let service_type = settings_connection
+            .get_string_setting("vpn", "service-type")
+            .await?
+            .unwrap_or_default();
+        let kind = service_type
+            .rsplit('.')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let vpn_connection = VpnConnection::new(
+            active_connection.get_path().as_str().try_into()?,
+            connection,
+        )
+        .await?;
+        (
+            kind,
+            vpn_connection.get_vpn_state().await?.to_string(),
+            vpn_connection.get_banner().await?,
+        )
+    } else {
+        (
+            connection_type,
+            active_connection.get_state().await?.to_string(),
+            "".to_string(),
+        )
+    };
+
+    // No IP configuration until the connection is activated, a tunnel can be IPv4 or IPv6 only
+    let mut addresses = vec![];
+    let ip4_gateway = match active_connection.get_ip4_config().await {
+        Ok(ip4_config) => {
+            match tokio::try_join!(ip4_config.get_gateway(), ip4_config.get_addresses()) {
+                Ok((gateway, ip4_addresses)) => {
+                    addresses.extend(ip4_addresses);
+                    gateway
+                }
+                Err(_) => "".to_string(),
+            }
+        }
+        Err(_) => "".to_string(),
+    };
+    let ip6_gateway = match active_connection.get_ip6_config().await {
+        Ok(ip6_config) => {
+            match tokio::try_join!(ip6_config.get_gateway(), ip6_config.get_addresses()) {
+                Ok((gateway, ip6_addresses)) => {
+                    addresses.extend(
+                        ip6_addresses
+                            .into_iter()
+                            .filter(|address| !address.is_link_local()),
+                    );
+                    gateway
+                }
+                Err(_) => "".to_string(),
+            }
+        }
+        Err(_) => "".to_string(),
+    };
+    let addresses: Vec<String> = addresses
+        .iter()
+        .map(|address| format!("{}/{}", address.address, address.mask_cird))
+        .collect();
+
+    Ok(Some(json!({
+        "name": active_connection.get_id().await?,
+        "type": kind,
+        "state": state,
+        "banner": banner,
+        "ip4Gateway": ip4_gateway,
+        "ip6Gateway": ip6_gateway,
+        "addresses": addresses,
+    })))
+}
+
+/// Activate the saved VPN or WireGuard profile `name`
+pub(crate) async fn up(name: &str) -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let nm = NetworkManager::new(&connection).await?;
+    let settings = Settings::new(&connection).await?;
+    let saved = settings
+        .get_connection_by_id(name)
+        .await?
+        .ok_or_else(|| format!("no saved connection named \"{}\"", name))?;
+    let connection_type = saved
+        .get_string_setting("connection", "type")
+        .await?
+        .unwrap_or_default();
+    if connection_type != "vpn" && connection_type != "wireguard" {
+        return Err(format!("\"{}\" is not a VPN or WireGuard connection", name).into());
+    }
+
+    let active_connection = nm.activate_connection(saved.get_path(), "/", "/").await?;
+    follow_activation(&active_connection, "name", name).await
+}
+
+/// Deactivate the active VPN or WireGuard connection `name`
+pub(crate) async fn down(name: &str) -> Result<(), Error> {
+    let connection = Connection::system().await?;
+
+    let nm = NetworkManager::new(&connection).await?;
+    for active_connection in nm.get_active_connections().await? {
+        let is_tunnel =
+            active_connection.is_vpn().await? || active_connection.get_type().await? == "wireguard";
+        if is_tunnel && active_connection.get_id().await? == name {
+            nm.deactivate_connection(&active_connection).await?;
+            println!("{}", json!({ "name": name, "state": "Deactivated" }));
+            return Ok(());
+        }
+    }
+    Err(format!("no active VPN or WireGuard connection named \"{}\"", name).into())
+}
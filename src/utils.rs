@@ -1,7 +1,10 @@
-use std::{collections::HashMap, error::Error};
+use std::collections::HashMap;
+
 use zbus::{zvariant::Value, Connection};
 
-pub(crate) async fn send_notification(connection: Connection) -> Result<(), Box<dyn Error>> {
+use crate::error::Error;
+
+pub(crate) async fn send_notification(connection: Connection) -> Result<(), Error> {
     let m = connection
         .call_method(
             Some("org.freedesktop.Notifications"),
@@ -20,7 +23,7 @@ pub(crate) async fn send_notification(connection: Connection) -> Result<(), Box<
             ),
         )
         .await?;
-    let reply: u32 = m.body().unwrap();
+    let reply: u32 = m.body()?;
     dbg!(reply);
     Ok(())
 }